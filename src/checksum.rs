@@ -0,0 +1,232 @@
+use crate::{error, Result};
+use digest::DynDigest;
+use regex::Regex;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncReadExt;
+
+// FileChecksum is the expected checksum of a downloaded file, as given by
+// the checksum query parameter.
+#[derive(Debug)]
+pub(crate) struct FileChecksum {
+    kind: &'static str,
+    value: Vec<u8>,
+    // verified is set once a file has been found to match, see
+    // Hasher::finish.
+    verified: AtomicBool,
+}
+
+impl FileChecksum {
+    // parse parses a checksum parameter of the form "type:value". If the
+    // type is omitted, it is guessed from the length of the value.
+    pub(crate) fn parse(checksum: &str) -> Result<FileChecksum> {
+        match checksum.split_once(':') {
+            Some((kind, value)) => FileChecksum::new(Some(kind), value),
+            None => FileChecksum::new(None, checksum),
+        }
+    }
+
+    // from_sums picks the checksum of filename out of the contents of a
+    // checksum file such as SHA256SUMS. Both the GNU coreutils format
+    // ("hash  filename") and the BSD format ("SHA256 (filename) = hash")
+    // are understood.
+    pub(crate) fn from_sums(sums: &str, filename: &str) -> Result<FileChecksum> {
+        lazy_static::lazy_static! {
+            static ref BSD_REGEXP: Regex =
+                Regex::new(r"^(\w+) ?\((.+)\) ?= ?([0-9a-fA-F]+)$").unwrap();
+        }
+
+        for line in sums.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(m) = BSD_REGEXP.captures(line) {
+                if strip_name(&m[2]) == filename {
+                    return FileChecksum::new(Some(&m[1].to_lowercase()), &m[3]);
+                }
+                continue;
+            }
+
+            // In the GNU format a "*" in front of the filename marks
+            // binary mode, which makes no difference to us.
+            if let Some((value, name)) = line.split_once(char::is_whitespace) {
+                let name = name.trim_start();
+                if strip_name(name.strip_prefix('*').unwrap_or(name)) == filename {
+                    return FileChecksum::new(None, value);
+                }
+            }
+        }
+
+        Err(error::not_found(format!(
+            "no checksum found for {:?} in checksum file",
+            filename
+        )))
+    }
+
+    // new checks the given hex encoded value against the checksum type,
+    // which is guessed from the length of the value if it isn't given.
+    fn new(kind: Option<&str>, value: &str) -> Result<FileChecksum> {
+        let kind = match kind {
+            Some(kind) => kind,
+            None => match value.len() {
+                32 => "md5",
+                40 => "sha1",
+                64 => "sha256",
+                128 => "sha512",
+                _ => {
+                    return Err(error::invalid_source(format!(
+                        "unknown type for checksum {}",
+                        value
+                    )))
+                }
+            },
+        };
+
+        // Make sure the type is supported before we go any further.
+        let kind = match kind {
+            "md5" => "md5",
+            "sha1" => "sha1",
+            "sha256" => "sha256",
+            "sha512" => "sha512",
+            _ => {
+                return Err(error::invalid_source(format!(
+                    "unsupported checksum type: {}",
+                    kind
+                )))
+            }
+        };
+
+        let value = hex::decode(value)
+            .map_err(|e| error::invalid_source(format!("invalid checksum: {}", e)))?;
+
+        let checksum = FileChecksum {
+            kind,
+            value,
+            verified: AtomicBool::new(false),
+        };
+        if checksum.digest().output_size() != checksum.value.len() {
+            return Err(error::invalid_source(format!(
+                "invalid {} checksum length: {}",
+                kind,
+                checksum.value.len()
+            )));
+        }
+
+        Ok(checksum)
+    }
+
+    // verify hashes the file at path and returns a checksum error if it
+    // doesn't match the expected value. This is for getters that don't
+    // stream the file, others check it as they receive it, see hasher.
+    pub(crate) async fn verify(&self, path: &Path) -> Result<()> {
+        let mut hasher = self.hasher();
+        hasher.read(path).await?;
+        hasher.finish(path)
+    }
+
+    // hasher returns a Hasher that checks a file against this checksum as
+    // it is received.
+    pub(crate) fn hasher(&self) -> Hasher<'_> {
+        Hasher {
+            checksum: self,
+            digest: self.digest(),
+        }
+    }
+
+    // is_verified returns whether a file has been found to match this
+    // checksum.
+    pub(crate) fn is_verified(&self) -> bool {
+        self.verified.load(Ordering::SeqCst)
+    }
+
+    fn digest(&self) -> Box<dyn DynDigest + Send> {
+        match self.kind {
+            "md5" => Box::new(md5::Md5::default()),
+            "sha1" => Box::new(sha1::Sha1::default()),
+            "sha256" => Box::new(sha2::Sha256::default()),
+            _ => Box::new(sha2::Sha512::default()),
+        }
+    }
+}
+
+// Hasher hashes a file while it is being received, to check it against a
+// FileChecksum once it is complete.
+pub(crate) struct Hasher<'a> {
+    checksum: &'a FileChecksum,
+    digest: Box<dyn DynDigest + Send>,
+}
+
+impl Hasher<'_> {
+    // update hashes the next chunk of the file.
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.digest.update(data);
+    }
+
+    // read hashes the contents of the file at path, e.g. the part of the
+    // file that was received earlier.
+    pub(crate) async fn read(&mut self, path: &Path) -> Result<()> {
+        let mut file = tokio::fs::File::open(path).await.map_err(error::io)?;
+
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await.map_err(error::io)?;
+            if n == 0 {
+                return Ok(());
+            }
+            self.digest.update(&buf[..n]);
+        }
+    }
+
+    // finish returns a checksum error naming the file by name if what was
+    // hashed doesn't match the expected value.
+    pub(crate) fn finish(self, name: impl fmt::Debug) -> Result<()> {
+        let checksum = self.checksum;
+        let actual = self.digest.finalize();
+        if *actual != *checksum.value {
+            return Err(error::checksum_mismatch(format!(
+                "{} checksums did not match for {:?}: expected {}, got {}",
+                checksum.kind,
+                name,
+                hex::encode(&checksum.value),
+                hex::encode(actual)
+            )));
+        }
+
+        checksum.verified.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+// copy copies the file at src to dst and returns its size. If there is a
+// checksum, the copy is made next to dst and only renamed into place once it
+// has been found to match, so that a bad file never replaces what is at dst.
+pub(crate) async fn copy(checksum: Option<&FileChecksum>, src: &Path, dst: &Path) -> Result<u64> {
+    let checksum = match checksum {
+        Some(checksum) => checksum,
+        None => return tokio::fs::copy(src, dst).await.map_err(error::io),
+    };
+
+    let parent = match dst.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let tmp = tempfile::Builder::new()
+        .prefix(".getter-")
+        .tempfile_in(parent)
+        .map_err(error::io)?;
+
+    let copied = tokio::fs::copy(src, tmp.path()).await.map_err(error::io)?;
+    let mut hasher = checksum.hasher();
+    hasher.read(tmp.path()).await?;
+    hasher.finish(src)?;
+
+    tmp.persist(dst).map_err(|e| error::io(e.error))?;
+    Ok(copied)
+}
+
+// strip_name removes a leading "./" from a filename in a checksum file.
+fn strip_name(name: &str) -> &str {
+    name.strip_prefix("./").unwrap_or(name)
+}
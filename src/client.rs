@@ -1,4 +1,20 @@
-use crate::ClientMode;
+use crate::checksum::FileChecksum;
+use crate::copy_dir::copy_dir;
+use crate::decompressor::{Decompressor, DECOMPRESSORS};
+use crate::detector::{detect_with, get_forced_getter, source_dir_subdir};
+use crate::getter::{Context, Getter};
+use crate::retry::RetryPolicy;
+use crate::{
+    error, CancellationToken, ClientMode, Detector, ErrorKind, ProgressListener, Result, Timeouts,
+    DETECTORS, GETTERS,
+};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use url::Url;
 
 /// Client is a client for downloading things.
 ///
@@ -6,17 +22,509 @@ use crate::ClientMode;
 /// Using a client directly allows more fine-grained control over how downloading
 /// is done, as well as customizing the protocols supported.
 #[derive(Debug)]
-pub(crate) struct Client {
+pub struct Client {
     /// Src is the source URL to get.
-    src: String,
+    pub src: String,
+
+    /// Dst is the path to save the downloaded thing as. If the mode is
+    /// [ClientMode::Dir] or [ClientMode::Any], this should be a directory.
+    pub dst: PathBuf,
+
+    /// Pwd is the working directory for detection. If this isn't set, some
+    /// detection may fail. Client does not default pwd to the current
+    /// working directory for security reasons.
+    pub pwd: String,
 
     /// Mode is the method of download the client will use. See [ClientMode]
     /// for documentation.
-    mode: ClientMode,
-    // Decompressors is the map of decompressors supported by this client.
-    // If this is nil, then the default value is the Decompressors global.
-    // Decompressors map[string]Decompressor
-    // Getters is the map of protocols supported by this client. If this
-    // is nil, then the default Getters variable will be used.
-    // getters: BTreeMap<&'static str, Box<dyn Getter<Stream = dyn StreamExt<Item = dyn Any>>>>,
+    pub mode: ClientMode,
+
+    /// Detectors is the list of detectors that are tried on the source.
+    /// By default this is the [DETECTORS] global.
+    pub detectors: Arc<Vec<Box<dyn Detector>>>,
+
+    /// Decompressors is the map of decompressors supported by this client.
+    /// By default this is the [DECOMPRESSORS] global.
+    pub decompressors: Arc<BTreeMap<&'static str, Box<dyn Decompressor>>>,
+
+    /// Getters is the map of protocols supported by this client. By default
+    /// this is the [GETTERS] global.
+    pub getters: Arc<BTreeMap<&'static str, Box<dyn Getter>>>,
+
+    /// Progress is notified about the progress of downloads and archive
+    /// extraction, if set.
+    pub progress: Option<Arc<dyn ProgressListener>>,
+
+    /// Retry decides whether and when getter calls that failed with a
+    /// retryable error are attempted again.
+    pub retry: RetryPolicy,
+
+    /// Timeouts limits how long connecting to servers, waiting for them and
+    /// the whole download may take.
+    pub timeouts: Timeouts,
+
+    /// Cancel aborts an in-flight [Client::get] when it is cancelled. Any
+    /// partial output is removed, unless the destination existed before.
+    pub cancel: CancellationToken,
+
+    /// Netrc is the path of the .netrc file that credentials for HTTP and
+    /// git over HTTPS are looked up in. When unset, the file named by
+    /// `$NETRC` or `~/.netrc` is used if it exists.
+    pub netrc: Option<PathBuf>,
+}
+
+impl Client {
+    /// Creates a client that downloads src into dst using the given mode and
+    /// the default detectors, decompressors and getters.
+    pub fn new<S, D>(src: S, dst: D, mode: ClientMode) -> Client
+    where
+        S: Into<String>,
+        D: Into<PathBuf>,
+    {
+        Client {
+            src: src.into(),
+            dst: dst.into(),
+            pwd: String::new(),
+            mode,
+            detectors: DETECTORS.clone(),
+            decompressors: DECOMPRESSORS.clone(),
+            getters: GETTERS.clone(),
+            progress: None,
+            retry: RetryPolicy::default(),
+            timeouts: Timeouts::default(),
+            cancel: CancellationToken::new(),
+            netrc: None,
+        }
+    }
+
+    /// Get downloads the configured source to the destination.
+    ///
+    /// Errors that occur once the source has been turned into a URL record
+    /// that URL, see [Error::url](crate::Error::url).
+    pub async fn get(&self) -> Result<()> {
+        let ctx = Context {
+            progress: self.progress.clone(),
+            timeouts: self.timeouts.clone(),
+            deadline: self.timeouts.total.map(|total| Instant::now() + total),
+            cancel: self.cancel.clone(),
+            netrc: self.netrc.clone(),
+            client: Some(Arc::new(self.derive(&self.src, &self.dst, self.mode))),
+            redirects: Vec::new(),
+            checksum: None,
+            clients: Default::default(),
+        };
+
+        let existed = self.dst.symlink_metadata().is_ok();
+
+        let result = self.get_with(&ctx).await;
+
+        // A download that was aborted half way through may have left some
+        // of its output behind, which is of no use to anyone.
+        if let Err(ref e) = result {
+            if matches!(e.kind(), ErrorKind::Timeout | ErrorKind::Cancelled) && !existed {
+                remove_all(&self.dst);
+            }
+        }
+
+        result
+    }
+
+    // get_with downloads the configured source to the destination as part
+    // of the download described by ctx.
+    pub(crate) async fn get_with(&self, ctx: &Context) -> Result<()> {
+        if self.mode == ClientMode::Invalid {
+            return Err(error::other("client mode must be set"));
+        }
+
+        let src = ctx
+            .run(detect_with(ctx, &self.src, &self.pwd, &self.detectors))
+            .await?;
+
+        let (force, src) = get_forced_getter(&src);
+
+        // If there is a subdir component, then we download the root
+        // separately into a temporary directory, then copy over the proper
+        // subdir.
+        let (src, subdir) = source_dir_subdir(src);
+
+        let url = Url::parse(&src).map_err(error::invalid_source)?;
+
+        self.get_url(ctx, force, url.clone(), &subdir)
+            .await
+            .map_err(|e| e.with_url(&url))
+    }
+
+    // get_url downloads the detected URL to the destination, using the
+    // forced getter if it is set.
+    async fn get_url(&self, ctx: &Context, force: &str, mut url: Url, subdir: &str) -> Result<()> {
+        let mut mode = self.mode;
+
+        let mut dst = self.dst.clone();
+        let mut subdir_tmp = None;
+        if !subdir.is_empty() {
+            let td = tempfile::tempdir().map_err(error::io)?;
+            dst = td.path().join("temp");
+            subdir_tmp = Some(td);
+        }
+
+        let force = if force.is_empty() {
+            url.scheme().to_string()
+        } else {
+            force.to_string()
+        };
+
+        let getter = self.getters.get(force.as_str()).ok_or_else(|| {
+            error::unsupported_scheme(format!("download not supported for scheme '{}'", force))
+        })?;
+
+        // Determine if we have an archive type. The parameter is removed
+        // since it is a magic parameter we don't want to pass on to the
+        // getter. If it isn't set, then we look at the filename instead.
+        let archive = match take_query_param(&mut url, "archive").as_deref() {
+            // Archive handling can be disabled entirely, even if the
+            // filename has a known extension.
+            Some("false") | Some("0") => None,
+            None | Some("") | Some("true") | Some("1") => self.archive_from_path(url.path()),
+            Some(archive) => Some(
+                self.decompressors
+                    .get_key_value(archive)
+                    .map(|(key, _)| *key)
+                    .ok_or_else(|| {
+                        error::invalid_source(format!("unsupported archive type '{}'", archive))
+                    })?,
+            ),
+        };
+
+        // Determine if we have a checksum to verify the downloaded file
+        // against. Like the archive, this is a magic parameter.
+        let checksum = match take_query_param(&mut url, "checksum").as_deref() {
+            None | Some("") => None,
+            Some(checksum) => match checksum.strip_prefix("file:") {
+                Some(checksum_url) => Some(self.checksum_from_file(ctx, checksum_url, &url).await?),
+                None => Some(FileChecksum::parse(checksum)?),
+            },
+        };
+
+        // If we have a decompressor, then we need to change the destination
+        // to download to a work directory next to the final, real path. We
+        // unarchive from there into the real path.
+        let decompress_dir = mode != ClientMode::File;
+        let mut decompress = None;
+        if let Some(archive) = archive {
+            let work = ArchiveDir::new(&self.dst)?;
+            let decompress_dst = std::mem::replace(&mut dst, work.path().join("archive"));
+            decompress = Some((archive, decompress_dst, work));
+            mode = ClientMode::File;
+        }
+
+        // Determine if we have a custom mode
+        if mode == ClientMode::Any {
+            mode = getter.client_mode(&url)?;
+
+            // If we're getting a file, then the file is saved into the
+            // destination directory with the basename of the URL.
+            if mode == ClientMode::File {
+                let filename = url
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| {
+                        error::invalid_source(format!("cannot determine filename for {}", url))
+                    })?;
+                dst = dst.join(filename);
+            }
+        }
+
+        if mode == ClientMode::File {
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent).map_err(error::io)?;
+            }
+
+            match checksum {
+                None => {
+                    ctx.run(self.retry.retry(|| getter.get_file(ctx, &dst, &url)))
+                        .await?
+                }
+                Some(checksum) => {
+                    // The getters of this crate check the file before they
+                    // move it into place, so a bad file never replaces what
+                    // is at dst. Others leave that to us, and have written
+                    // dst already, so a bad file is removed again.
+                    let checksum = Arc::new(checksum);
+                    let ctx = Context {
+                        checksum: Some(checksum.clone()),
+                        ..ctx.clone()
+                    };
+                    ctx.run(self.retry.retry(|| getter.get_file(&ctx, &dst, &url)))
+                        .await?;
+                    if !checksum.is_verified() {
+                        if let Err(e) = checksum.verify(&dst).await {
+                            let _ = fs::remove_file(&dst);
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            match decompress {
+                // If we're not decompressing, then we're done.
+                None => return Ok(()),
+                Some((archive, decompress_dst, work)) => {
+                    let decompressors = self.decompressors.clone();
+                    let progress = self.progress.clone();
+                    let src = dst.clone();
+                    let target = decompress_dst.clone();
+                    let result = tokio::task::spawn_blocking(move || {
+                        decompressors[archive].decompress(
+                            &target,
+                            &src,
+                            decompress_dir,
+                            progress.as_deref(),
+                        )
+                    })
+                    .await
+                    .map_err(error::other);
+
+                    // The archive is complete, so there is nothing to resume
+                    // anymore, whether or not it could be unpacked.
+                    work.remove();
+                    result??;
+
+                    // Swap the information back
+                    dst = decompress_dst;
+
+                    // If we're still only getting a file, then we're done.
+                    if !decompress_dir {
+                        return Ok(());
+                    }
+                }
+            }
+        } else {
+            if checksum.is_some() {
+                return Err(error::invalid_source(
+                    "checksum cannot be specified for directory download",
+                ));
+            }
+
+            // We're downloading a directory, which might require a bit more
+            // work if we're specifying a subdir.
+            ctx.run(self.retry.retry(|| getter.get(ctx, &dst, &url)))
+                .await?;
+        }
+
+        // If we have a subdir, copy that over
+        if subdir_tmp.is_some() {
+            if self.dst.exists() {
+                fs::remove_dir_all(&self.dst).map_err(error::io)?;
+            }
+
+            let subdir = subdir_glob(&dst, subdir)?;
+
+            fs::create_dir_all(&self.dst).map_err(error::io)?;
+
+            let real_dst = self.dst.clone();
+            tokio::task::spawn_blocking(move || copy_dir(&real_dst, &subdir, false))
+                .await
+                .map_err(error::other)?
+                .map_err(error::io)?;
+        }
+
+        Ok(())
+    }
+
+    // checksum_from_file downloads the checksum file at checksum_url using
+    // the getters of this client, and returns the checksum it lists for the
+    // file at url.
+    async fn checksum_from_file(
+        &self,
+        ctx: &Context,
+        checksum_url: &str,
+        url: &Url,
+    ) -> Result<FileChecksum> {
+        let filename = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| {
+                error::invalid_source(format!("cannot determine filename for {}", url))
+            })?;
+
+        let td = tempfile::tempdir().map_err(error::io)?;
+        let dst = td.path().join("checksum");
+
+        let client = self.derive(checksum_url, &dst, ClientMode::File);
+        // The future is boxed since get_with calls back into this function.
+        // The checksum file counts towards the deadline of this download.
+        Box::pin(client.get_with(ctx)).await?;
+
+        let sums = fs::read_to_string(&dst).map_err(error::io)?;
+        FileChecksum::from_sums(&sums, filename)
+    }
+
+    // derive returns a client with the settings of this one that downloads
+    // src into dst using the given mode.
+    pub(crate) fn derive(&self, src: &str, dst: &Path, mode: ClientMode) -> Client {
+        Client {
+            src: src.to_string(),
+            dst: dst.to_path_buf(),
+            pwd: self.pwd.clone(),
+            mode,
+            detectors: self.detectors.clone(),
+            decompressors: self.decompressors.clone(),
+            getters: self.getters.clone(),
+            progress: self.progress.clone(),
+            retry: self.retry.clone(),
+            timeouts: self.timeouts.clone(),
+            cancel: self.cancel.clone(),
+            netrc: self.netrc.clone(),
+        }
+    }
+
+    // archive_from_path returns the key of the decompressor matching the
+    // longest extension of the given path, if any.
+    fn archive_from_path(&self, path: &str) -> Option<&'static str> {
+        self.decompressors
+            .keys()
+            .filter(|ext| path.ends_with(&format!(".{}", ext)))
+            .max_by_key(|ext| ext.len())
+            .copied()
+    }
+}
+
+// take_query_param removes the given parameter from the query string of the
+// URL and returns its value, if it was set.
+fn take_query_param(url: &mut Url, key: &str) -> Option<String> {
+    let mut value = None;
+    let mut rest = Vec::new();
+    for (k, v) in url.query_pairs() {
+        if k == key {
+            value = Some(v.into_owned());
+        } else {
+            rest.push((k.into_owned(), v.into_owned()));
+        }
+    }
+
+    if value.is_some() {
+        if rest.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(rest);
+        }
+    }
+
+    value
+}
+
+// ArchiveDir is the work directory an archive is downloaded to before it is
+// unpacked into the destination. It sits next to the destination and is
+// named after it, so that a download that failed half way through is found
+// and resumed by the next attempt to get the same destination.
+struct ArchiveDir {
+    path: PathBuf,
+    // tmp is only set for destinations without a name, such as ".", whose
+    // downloads can't be resumed.
+    _tmp: Option<tempfile::TempDir>,
+}
+
+impl ArchiveDir {
+    fn new(dst: &Path) -> Result<ArchiveDir> {
+        match dst.file_name() {
+            Some(name) => {
+                let mut dir = std::ffi::OsString::from(".");
+                dir.push(name);
+                dir.push(".getter-archive");
+                Ok(ArchiveDir {
+                    path: dst.with_file_name(dir),
+                    _tmp: None,
+                })
+            }
+            None => {
+                let td = tempfile::tempdir().map_err(error::io)?;
+                Ok(ArchiveDir {
+                    path: td.path().to_path_buf(),
+                    _tmp: Some(td),
+                })
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // remove removes the directory along with the archive in it.
+    fn remove(&self) {
+        remove_all(&self.path);
+    }
+}
+
+// Whatever a failed download left in the directory is kept for the next
+// attempt, but an empty directory is of no use.
+impl Drop for ArchiveDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir(&self.path);
+    }
+}
+
+// remove_all removes whatever is at path, if anything. Errors are ignored,
+// since this is only used to clean up after a failure.
+fn remove_all(path: &Path) {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => {
+            let _ = fs::remove_dir_all(path);
+        }
+        Ok(_) => {
+            let _ = fs::remove_file(path);
+        }
+        Err(_) => {}
+    }
+}
+
+// subdir_glob returns the actual subdir with globbing processed.
+//
+// dst should be a destination directory that is already populated (the
+// download is complete) and subdir should be the unprocessed subdirectory.
+// This will return the resolved path or an error if the subdirectory
+// doesn't exist or the glob matches more than one path.
+fn subdir_glob(dst: &Path, subdir: &str) -> Result<PathBuf> {
+    let mut matches = vec![dst.to_path_buf()];
+
+    for component in subdir.split('/').filter(|c| !c.is_empty()) {
+        if !component.contains(['*', '?']) {
+            matches = matches
+                .into_iter()
+                .map(|m| m.join(component))
+                .filter(|m| m.exists())
+                .collect();
+            continue;
+        }
+
+        let pattern = format!(
+            "^{}$",
+            regex::escape(component)
+                .replace(r"\*", ".*")
+                .replace(r"\?", ".")
+        );
+        let pattern = Regex::new(&pattern).map_err(error::invalid_source)?;
+
+        let mut next = Vec::new();
+        for m in matches {
+            for entry in fs::read_dir(&m).map_err(error::io)? {
+                let entry = entry.map_err(error::io)?;
+                if pattern.is_match(&entry.file_name().to_string_lossy()) {
+                    next.push(entry.path());
+                }
+            }
+        }
+        matches = next;
+    }
+
+    match matches.len() {
+        0 => Err(error::not_found(format!("subdir {:?} not found", subdir))),
+        1 => Ok(matches.remove(0)),
+        _ => Err(error::invalid_source(format!(
+            "subdir {:?} matches multiple paths",
+            subdir
+        ))),
+    }
 }
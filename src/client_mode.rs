@@ -1,20 +1,23 @@
-#[derive(Copy, Clone, Debug)]
+/// ClientMode is the mode that the client operates in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ClientMode {
+    /// Invalid is the zero value of the mode. A client must be given one of
+    /// the other modes before it can download anything.
     Invalid,
 
-    // ClientModeAny downloads anything it can. In this mode, dst must
-    // be a directory. If src is a file, it is saved into the directory
-    // with the basename of the URL. If src is a directory or archive,
-    // it is unpacked directly into dst.
+    /// Any downloads anything it can. In this mode, dst must
+    /// be a directory. If src is a file, it is saved into the directory
+    /// with the basename of the URL. If src is a directory or archive,
+    /// it is unpacked directly into dst.
     Any,
 
-    // ClientModeFile downloads a single file. In this mode, dst must
-    // be a file path (doesn't have to exist). src must point to a single
-    // file. It is saved as dst.
+    /// File downloads a single file. In this mode, dst must
+    /// be a file path (doesn't have to exist). src must point to a single
+    /// file. It is saved as dst.
     File,
 
-    // ClientModeDir downloads a directory. In this mode, dst must be
-    // a directory path (doesn't have to exist). src must point to an
-    // archive or directory (such as in s3).
+    /// Dir downloads a directory. In this mode, dst must be
+    /// a directory path (doesn't have to exist). src must point to an
+    /// archive or directory (such as in s3).
     Dir,
 }
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// copy_dir copies the src directory contents into dst. Both directories
+// should already exist.
+//
+// If ignore_dot is set to true, then dot-prefixed files/folders are ignored.
+pub(crate) fn copy_dir(dst: &Path, src: &Path, ignore_dot: bool) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        if ignore_dot && name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let src_path = entry.path();
+        let dst_path = dst.join(&name);
+
+        // Symlinks are copied as links to the same target. Following them
+        // would fail for dangling links and never end for links to one of
+        // their parents.
+        let metadata = fs::symlink_metadata(&src_path)?;
+        if metadata.file_type().is_symlink() {
+            copy_symlink(&dst_path, &src_path)?;
+        } else if metadata.is_dir() {
+            fs::create_dir_all(&dst_path)?;
+            fs::set_permissions(&dst_path, metadata.permissions())?;
+            copy_dir(&dst_path, &src_path, ignore_dot)?;
+        } else {
+            fs::copy(&src_path, &dst_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+// copy_symlink creates a symlink at dst with the same target as the one at
+// src, replacing whatever file or link is at dst.
+fn copy_symlink(dst: &Path, src: &Path) -> io::Result<()> {
+    let target = fs::read_link(src)?;
+
+    if let Ok(metadata) = fs::symlink_metadata(dst) {
+        if !metadata.is_dir() {
+            fs::remove_file(dst)?;
+        }
+    }
+
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(&target, dst);
+
+    // Windows needs to know whether the target is a directory, which it
+    // isn't as far as we can tell if the link is dangling.
+    #[cfg(windows)]
+    return if fs::metadata(src).map(|m| m.is_dir()).unwrap_or(false) {
+        std::os::windows::fs::symlink_dir(&target, dst)
+    } else {
+        std::os::windows::fs::symlink_file(&target, dst)
+    };
+}
@@ -0,0 +1,121 @@
+//! Decompressors unpack archives that have been downloaded by a getter.
+
+use crate::{ProgressListener, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+cfg_if::cfg_if! {
+    if #[cfg(not(target_arch = "wasm32"))] {
+        use crate::error;
+        use crate::progress::FileProgress;
+        use std::fs::{self, File};
+        use std::io::{self, Read, Write};
+
+        mod bzip2;
+        pub use self::bzip2::{Bzip2Decompressor, TarBzip2Decompressor};
+
+        mod gzip;
+        pub use gzip::{GzipDecompressor, TarGzipDecompressor};
+
+        mod tar;
+        pub use self::tar::TarDecompressor;
+
+        mod xz;
+        pub use xz::{TarXzDecompressor, XzDecompressor};
+
+        mod zip;
+        pub use self::zip::ZipDecompressor;
+    }
+}
+
+lazy_static::lazy_static! {
+    #[allow(missing_docs)]
+    pub static ref DECOMPRESSORS: Arc<BTreeMap<&'static str, Box<dyn Decompressor>>> = {
+        #[allow(unused_mut)]
+        let mut map: BTreeMap<&str, Box<dyn Decompressor>> = BTreeMap::new();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            map.insert("bz2", Box::new(Bzip2Decompressor));
+            map.insert("gz", Box::new(GzipDecompressor));
+            map.insert("xz", Box::new(XzDecompressor));
+            map.insert("tar", Box::new(TarDecompressor));
+            map.insert("tar.bz2", Box::new(TarBzip2Decompressor));
+            map.insert("tar.gz", Box::new(TarGzipDecompressor));
+            map.insert("tar.xz", Box::new(TarXzDecompressor));
+            map.insert("tbz2", Box::new(TarBzip2Decompressor));
+            map.insert("tgz", Box::new(TarGzipDecompressor));
+            map.insert("txz", Box::new(TarXzDecompressor));
+            map.insert("zip", Box::new(ZipDecompressor));
+        }
+
+        Arc::new(map)
+    };
+}
+
+/// Decompressor defines the interface that must be implemented to add
+/// support for decompressing a type.
+///
+/// Decompressors are keyed by the file extension they handle, without the
+/// leading dot (e.g. "tar.gz").
+pub trait Decompressor: fmt::Debug + Sync + Send + 'static {
+    /// Decompress should decompress src to dst. dir specifies whether dst
+    /// is a directory or single file. src is guaranteed to be a single file
+    /// that exists. dst is not guaranteed to exist already.
+    ///
+    /// If progress is set, it is notified about every file that is
+    /// extracted.
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()>;
+}
+
+// copy_reader copies the contents of src into a new file at dst, creating
+// the parent directories if needed. The copy is reported to progress as the
+// named file.
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_reader<R: Read>(
+    dst: &Path,
+    mut src: R,
+    name: &str,
+    total: Option<u64>,
+    progress: Option<&dyn ProgressListener>,
+) -> Result<()> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).map_err(error::decompression)?;
+    }
+
+    let mut file = File::create(dst).map_err(error::decompression)?;
+    let mut file_progress = FileProgress::start(progress, name, total);
+
+    let mut buf = vec![0; 32 * 1024];
+    loop {
+        let n = match src.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(error::decompression(e)),
+        };
+        file.write_all(&buf[..n]).map_err(error::decompression)?;
+        file_progress.add(n as u64);
+    }
+
+    file_progress.finish();
+
+    Ok(())
+}
+
+// file_name returns the name single-file decompressors report their output
+// under.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
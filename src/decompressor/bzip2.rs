@@ -0,0 +1,62 @@
+use super::tar::untar;
+use super::{copy_reader, file_name, Decompressor};
+use crate::{error, ProgressListener, Result};
+use bzip2_rs::DecoderReader;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Bzip2Decompressor is an implementation of Decompressor that can
+/// decompress bz2 files.
+#[derive(Copy, Clone, Debug)]
+pub struct Bzip2Decompressor;
+
+impl Decompressor for Bzip2Decompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        // Directory isn't supported at all
+        if dir {
+            return Err(error::decompression(
+                "bzip2-compressed files can only unarchive to a single file",
+            ));
+        }
+
+        let file = File::open(src).map_err(error::decompression)?;
+        copy_reader(
+            dst,
+            DecoderReader::new(BufReader::new(file)),
+            &file_name(dst),
+            None,
+            progress,
+        )
+    }
+}
+
+/// TarBzip2Decompressor is an implementation of Decompressor that can
+/// unpack tar.bz2 files.
+#[derive(Copy, Clone, Debug)]
+pub struct TarBzip2Decompressor;
+
+impl Decompressor for TarBzip2Decompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        let file = File::open(src).map_err(error::decompression)?;
+        untar(
+            DecoderReader::new(BufReader::new(file)),
+            dst,
+            src,
+            dir,
+            progress,
+        )
+    }
+}
@@ -0,0 +1,62 @@
+use super::tar::untar;
+use super::{copy_reader, file_name, Decompressor};
+use crate::{error, ProgressListener, Result};
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// GzipDecompressor is an implementation of Decompressor that can
+/// decompress gzip files.
+#[derive(Copy, Clone, Debug)]
+pub struct GzipDecompressor;
+
+impl Decompressor for GzipDecompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        // Directory isn't supported at all
+        if dir {
+            return Err(error::decompression(
+                "gzip-compressed files can only unarchive to a single file",
+            ));
+        }
+
+        let file = File::open(src).map_err(error::decompression)?;
+        copy_reader(
+            dst,
+            GzDecoder::new(BufReader::new(file)),
+            &file_name(dst),
+            None,
+            progress,
+        )
+    }
+}
+
+/// TarGzipDecompressor is an implementation of Decompressor that can
+/// unpack tar.gz files.
+#[derive(Copy, Clone, Debug)]
+pub struct TarGzipDecompressor;
+
+impl Decompressor for TarGzipDecompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        let file = File::open(src).map_err(error::decompression)?;
+        untar(
+            GzDecoder::new(BufReader::new(file)),
+            dst,
+            src,
+            dir,
+            progress,
+        )
+    }
+}
@@ -0,0 +1,108 @@
+use super::Decompressor;
+use crate::progress::FileProgress;
+use crate::{error, ProgressListener, Result};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// TarDecompressor is an implementation of Decompressor that can
+/// unpack tar files.
+#[derive(Copy, Clone, Debug)]
+pub struct TarDecompressor;
+
+impl Decompressor for TarDecompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        let file = File::open(src).map_err(error::decompression)?;
+        untar(file, dst, src, dir, progress)
+    }
+}
+
+// untar is a shared helper for untarring an archive. The reader should
+// provide the uncompressed tar contents.
+pub(crate) fn untar<R: Read>(
+    input: R,
+    dst: &Path,
+    src: &Path,
+    dir: bool,
+    progress: Option<&dyn ProgressListener>,
+) -> Result<()> {
+    let mut archive = tar::Archive::new(input);
+    let mut done = false;
+
+    if dir {
+        fs::create_dir_all(dst).map_err(error::decompression)?;
+    }
+
+    for entry in archive.entries().map_err(error::decompression)? {
+        let mut entry = entry.map_err(error::decompression)?;
+        let entry_type = entry.header().entry_type();
+        let path = entry.path().map_err(error::decompression)?.into_owned();
+        let size = entry.size();
+
+        if !dir {
+            // We have a single file, so we can only have one entry and it
+            // must be a regular file.
+            if entry_type.is_dir() {
+                return Err(error::decompression(format!(
+                    "expected a single file: {}",
+                    src.display()
+                )));
+            }
+            if !entry_type.is_file() {
+                continue;
+            }
+            if done {
+                return Err(error::decompression(format!(
+                    "expected a single file, got multiple: {}",
+                    src.display()
+                )));
+            }
+
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent).map_err(error::decompression)?;
+            }
+            let mut file_progress =
+                FileProgress::start(progress, path.to_string_lossy(), Some(size));
+            entry.unpack(dst).map_err(error::decompression)?;
+            file_progress.add(size);
+            file_progress.finish();
+            done = true;
+            continue;
+        }
+
+        // Only files are reported, directories and links are instant.
+        let file_progress = entry_type
+            .is_file()
+            .then(|| FileProgress::start(progress, path.to_string_lossy(), Some(size)));
+
+        // unpack_in refuses to write outside of dst, which protects us from
+        // entries like "../../etc/passwd".
+        if !entry.unpack_in(dst).map_err(error::decompression)? {
+            return Err(error::decompression(format!(
+                "entry contains '..': {}",
+                path.display()
+            )));
+        }
+        if let Some(mut file_progress) = file_progress {
+            file_progress.add(size);
+            file_progress.finish();
+        }
+        done = true;
+    }
+
+    // If we didn't unpack anything, then the archive was empty.
+    if !done && !dir {
+        return Err(error::decompression(format!(
+            "empty archive: {}",
+            src.display()
+        )));
+    }
+
+    Ok(())
+}
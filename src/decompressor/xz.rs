@@ -0,0 +1,62 @@
+use super::tar::untar;
+use super::{copy_reader, file_name, Decompressor};
+use crate::{error, ProgressListener, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use xz2::read::XzDecoder;
+
+/// XzDecompressor is an implementation of Decompressor that can
+/// decompress xz files.
+#[derive(Copy, Clone, Debug)]
+pub struct XzDecompressor;
+
+impl Decompressor for XzDecompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        // Directory isn't supported at all
+        if dir {
+            return Err(error::decompression(
+                "xz-compressed files can only unarchive to a single file",
+            ));
+        }
+
+        let file = File::open(src).map_err(error::decompression)?;
+        copy_reader(
+            dst,
+            XzDecoder::new(BufReader::new(file)),
+            &file_name(dst),
+            None,
+            progress,
+        )
+    }
+}
+
+/// TarXzDecompressor is an implementation of Decompressor that can
+/// unpack tar.xz files.
+#[derive(Copy, Clone, Debug)]
+pub struct TarXzDecompressor;
+
+impl Decompressor for TarXzDecompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        let file = File::open(src).map_err(error::decompression)?;
+        untar(
+            XzDecoder::new(BufReader::new(file)),
+            dst,
+            src,
+            dir,
+            progress,
+        )
+    }
+}
@@ -0,0 +1,89 @@
+use super::{copy_reader, Decompressor};
+use crate::{error, ProgressListener, Result};
+use std::fs::{self, File};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// ZipDecompressor is an implementation of Decompressor that can
+/// unpack zip files.
+#[derive(Copy, Clone, Debug)]
+pub struct ZipDecompressor;
+
+impl Decompressor for ZipDecompressor {
+    fn decompress(
+        &self,
+        dst: &Path,
+        src: &Path,
+        dir: bool,
+        progress: Option<&dyn ProgressListener>,
+    ) -> Result<()> {
+        let file = File::open(src).map_err(error::decompression)?;
+        let mut archive = ZipArchive::new(file).map_err(error::decompression)?;
+
+        // Check the zip integrity
+        if archive.is_empty() {
+            // Empty archive
+            return Err(error::decompression(format!(
+                "empty archive: {}",
+                src.display()
+            )));
+        }
+        if !dir && archive.len() > 1 {
+            return Err(error::decompression(format!(
+                "expected a single file: {}",
+                src.display()
+            )));
+        }
+
+        if dir {
+            fs::create_dir_all(dst).map_err(error::decompression)?;
+        }
+
+        for i in 0..archive.len() {
+            let entry = archive.by_index(i).map_err(error::decompression)?;
+            let name = entry.name().to_string();
+            let size = entry.size();
+
+            if !dir {
+                if entry.is_dir() {
+                    return Err(error::decompression(format!(
+                        "expected a single file: {}",
+                        src.display()
+                    )));
+                }
+                return copy_reader(dst, entry, &name, Some(size), progress);
+            }
+
+            // enclosed_name refuses names that would escape dst, such as
+            // "../../etc/passwd".
+            let path = match entry.enclosed_name() {
+                Some(name) => dst.join(name),
+                None => {
+                    return Err(error::decompression(format!(
+                        "entry contains '..': {}",
+                        name
+                    )))
+                }
+            };
+
+            if entry.is_dir() {
+                fs::create_dir_all(&path).map_err(error::decompression)?;
+                continue;
+            }
+
+            #[cfg(unix)]
+            let mode = entry.unix_mode();
+
+            copy_reader(&path, entry, &name, Some(size), progress)?;
+
+            #[cfg(unix)]
+            if let Some(mode) = mode {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o777))
+                    .map_err(error::decompression)?;
+            }
+        }
+
+        Ok(())
+    }
+}
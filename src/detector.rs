@@ -1,6 +1,11 @@
+//! Detectors turn shorthand source strings into proper URLs that the
+//! getters can understand.
+
+use crate::getter::Context;
 use crate::{error, Result};
 use async_trait::async_trait;
 use regex::Regex;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Arc;
 use url::Url;
@@ -26,16 +31,35 @@ pub use github::GitHubDetector;
 mod gitlab;
 pub use gitlab::GitLabDetector;
 
+mod s3;
+pub use s3::S3Detector;
+
+mod gcs;
+pub use gcs::GCSDetector;
+
+mod oci;
+pub use oci::OCIDetector;
+
 lazy_static::lazy_static! {
     #[allow(missing_docs)]
     pub static ref DETECTORS: Arc<Vec<Box<dyn Detector>>> = Arc::new(vec![
         Box::new(GitHubDetector),
         Box::new(GitLabDetector),
         Box::new(GitDetector),
-        Box::new(BitBucketDetector),
+        Box::new(BitBucketDetector::default()),
+        Box::new(S3Detector),
+        Box::new(GCSDetector),
         #[cfg(not(target_arch = "wasm32"))]
         Box::new(FileDetector),
     ]);
+
+    // FORCED_DETECTORS are the detectors of getters whose sources aren't
+    // URLs. They take over from the others when their getter is forced.
+    static ref FORCED_DETECTORS: BTreeMap<&'static str, Box<dyn Detector>> = {
+        let mut map: BTreeMap<&str, Box<dyn Detector>> = BTreeMap::new();
+        map.insert("oci", Box::new(OCIDetector));
+        map
+    };
 }
 
 /// Detector defines the interface that an invalid URL or a URL with a blank
@@ -46,9 +70,16 @@ pub trait Detector: fmt::Debug + Sync + Send + 'static {
     /// Detect will detect whether the string matches a known pattern to
     /// turn it into a proper URL.
     async fn detect(&self, src: &str, pwd: &str) -> Result<(String, bool)>;
+
+    /// DetectWith is like detect, but is given the [Context] of the download
+    /// that the source is detected for, e.g. to honour its settings when
+    /// asking a server about the source. By default it calls detect.
+    async fn detect_with(&self, _ctx: &Context, src: &str, pwd: &str) -> Result<(String, bool)> {
+        self.detect(src, pwd).await
+    }
 }
 
-fn get_forced_getter(src: &str) -> (&str, &str) {
+pub(crate) fn get_forced_getter(src: &str) -> (&str, &str) {
     lazy_static::lazy_static! {
         static ref FORCED_REGEXP: Regex = Regex::new(r"^([A-Za-z0-9]+)::(.+)$").unwrap();
     }
@@ -72,7 +103,7 @@ fn get_forced_getter(src: &str) -> (&str, &str) {
 //   proto://dom.com/path//*?q=p     => proto://dom.com/path?q=p, "*"
 //   proto://dom.com/path//path2?q=p => proto://dom.com/path?q=p, "path2"
 //
-fn source_dir_subdir(src: &str) -> (String, String) {
+pub(crate) fn source_dir_subdir(src: &str) -> (String, String) {
     // URL might contain another url in query parameters
     let mut stop = src.len();
     if let Some(idx) = src.find('?') {
@@ -118,19 +149,36 @@ fn source_dir_subdir(src: &str) -> (String, String) {
 /// This is safe to be called with an already valid source string: Detect
 /// will just return it.
 pub async fn detect(src: &str, pwd: &str, detectors: &[Box<dyn Detector>]) -> Result<String> {
+    detect_with(&Context::default(), src, pwd, detectors).await
+}
+
+// detect_with is like detect, but for the download described by ctx, see
+// Detector::detect_with.
+pub(crate) async fn detect_with(
+    ctx: &Context,
+    src: &str,
+    pwd: &str,
+    detectors: &[Box<dyn Detector>],
+) -> Result<String> {
     let (get_force, get_src) = get_forced_getter(src);
 
     // Separate out the subdir if there is one, we don't pass that to detect
     let (get_src, mut subdir) = source_dir_subdir(get_src);
 
-    if let Ok(url) = Url::parse(&get_src) {
-        if url.scheme() != "" {
-            return Ok(src.to_owned());
+    let detectors = match FORCED_DETECTORS.get(get_force) {
+        Some(detector) => std::slice::from_ref(detector),
+        None => {
+            if let Ok(url) = Url::parse(&get_src) {
+                if url.scheme() != "" {
+                    return Ok(src.to_owned());
+                }
+            }
+            detectors
         }
-    }
+    };
 
     for detector in detectors {
-        let (result, ok) = detector.detect(&get_src, pwd).await?;
+        let (result, ok) = detector.detect_with(ctx, &get_src, pwd).await?;
         if !ok {
             continue;
         }
@@ -154,7 +202,7 @@ pub async fn detect(src: &str, pwd: &str, detectors: &[Box<dyn Detector>]) -> Re
                     url.set_path(&format!("{}//{}", url.path(), subdir));
                     url.to_string()
                 })
-                .map_err(error::detector)?;
+                .map_err(error::invalid_source)?;
         }
 
         // Preserve the forced getter if it exists. We try to use the
@@ -169,5 +217,8 @@ pub async fn detect(src: &str, pwd: &str, detectors: &[Box<dyn Detector>]) -> Re
         return Ok(result);
     }
 
-    Err(error::detector(format!("Invalid source string `{}`", src)))
+    Err(error::invalid_source(format!(
+        "Invalid source string `{}`",
+        src
+    )))
 }
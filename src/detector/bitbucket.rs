@@ -1,7 +1,7 @@
 use super::Detector;
+use crate::getter::Context;
 use crate::{error, Result};
 use async_trait::async_trait;
-use core::str::FromStr;
 use serde::Deserialize;
 use url::Url;
 
@@ -12,43 +12,50 @@ struct BitBucketResponse {
 
 /// BitBucketDetector implements Detector to detect BitBucket URLs and turn
 /// them into URLs that the Git or Hg Getter can understand.
-#[derive(Copy, Clone, Debug)]
-pub struct BitBucketDetector;
+///
+/// The BitBucket API is asked whether a repository uses Git or Hg. Private
+/// repositories need credentials for that, which are sent as headers like
+/// those of the HttpGetter, e.g. a `BITBUCKET_TOKEN` from the environment.
+#[derive(Clone, Debug, Default)]
+pub struct BitBucketDetector {
+    /// Tls configures how the connection to the BitBucket API is verified,
+    /// see [HttpGetter::tls](crate::getter::HttpGetter::tls).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub tls: crate::TlsConfig,
+
+    /// Headers are extra headers sent to the BitBucket API, see
+    /// [HttpGetter::headers](crate::getter::HttpGetter::headers).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub headers: Vec<crate::HostHeaders>,
+}
 
 impl BitBucketDetector {
     #[cfg(not(target_arch = "wasm32"))]
-    async fn detect_http(src: &str) -> Result<(String, bool)> {
-        use hyper::body::Buf;
-        use hyper::{Client, Uri};
-        use hyper_tls::HttpsConnector;
+    async fn detect_http(&self, ctx: &Context, src: &str) -> Result<(String, bool)> {
+        use crate::RetryPolicy;
 
-        let mut url = Url::parse(&format!("https://{}", src)).map_err(error::detector)?;
+        let mut url = Url::parse(&format!("https://{}", src)).map_err(error::invalid_source)?;
 
         // We need to get info on this BitBucket repository to determine whether
         // it is Git or Hg.
-        let info_url = format!("https://api.bitbucket.org/2.0/repositories{}", url.path());
-
-        let https = HttpsConnector::new();
-        let client = Client::builder().build::<_, hyper::Body>(https);
-
-        let uri = Uri::from_str(&info_url).map_err(error::detector)?;
-
-        let resp = client.get(uri).await.map_err(error::detector)?;
-
-        if resp.status() == 403 {
-            return Err(error::detector(
-                "shorthand BitBucket URL can't be used for private repos, please use a full URL"
-                    .to_string(),
-            ));
-        }
-
-        let body = hyper::body::aggregate(resp)
-            .await
-            .map_err(error::detector)?;
-        let info: BitBucketResponse =
-            serde_json::from_reader(body.reader()).map_err(error::detector)?;
+        let info_url = Url::parse(&format!(
+            "https://api.bitbucket.org/2.0/repositories{}",
+            url.path()
+        ))
+        .map_err(error::invalid_source)?;
+
+        // The API is flaky at times, so retry transient failures like the
+        // client retries downloads, within the timeouts of the download that
+        // the source is detected for.
+        let retry = match ctx.client {
+            Some(ref client) => client.retry.clone(),
+            None => RetryPolicy::default(),
+        };
+        let info = ctx
+            .run(retry.retry(|| self.fetch_info(ctx, &info_url)))
+            .await?;
 
-        return match info.scm.as_str() {
+        match info.scm.as_str() {
             "git" => {
                 if !url.path().ends_with(".git") {
                     url.set_path(&format!("{}.git", url.path()))
@@ -57,28 +64,74 @@ impl BitBucketDetector {
                 Ok((format!("git::{}", url), true))
             }
             "hg" => Ok((format!("hg::{}", url), true)),
-            _ => Err(error::detector(format!(
+            _ => Err(error::other(format!(
                 "unknown BitBucket SCM type: {}",
                 info.scm
             ))),
-        };
+        }
+    }
+
+    // fetch_info asks the BitBucket API about the repository at info_url,
+    // giving up when it doesn't answer within the timeouts of ctx.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn fetch_info(&self, ctx: &Context, info_url: &Url) -> Result<BitBucketResponse> {
+        use crate::timeout;
+        use hyper::body::Buf;
+        use hyper::Request;
+
+        let timeouts = &ctx.timeouts;
+        let client = ctx.clients.get(&self.tls, timeouts.connect)?;
+
+        let mut req = Request::get(info_url.as_str())
+            .body(hyper::Body::empty())
+            .map_err(error::invalid_source)?;
+        *req.headers_mut() = crate::headers::headers(&self.headers, None, info_url)
+            .map_err(|e| e.with_url(info_url))?;
+
+        let resp = timeout::read(timeouts.read, client.request(req))
+            .await
+            .and_then(|resp| resp.map_err(timeout::network))
+            .map_err(|e| e.with_url(info_url))?;
+
+        if !resp.status().is_success() {
+            let code = resp.status().as_u16();
+            let message = matches!(code, 401 | 403).then(|| {
+                "shorthand BitBucket URL can't be used for private repos without credentials, \
+                 please set BITBUCKET_TOKEN or use a full URL"
+                    .to_string()
+            });
+            return Err(error::status(code, message)
+                .with_retry_after(crate::retry::retry_after(resp.headers()))
+                .with_url(info_url));
+        }
+
+        let body = timeout::read(timeouts.read, hyper::body::aggregate(resp))
+            .await
+            .and_then(|body| body.map_err(timeout::network))
+            .map_err(|e| e.with_url(info_url))?;
+
+        serde_json::from_reader(body.reader()).map_err(|e| error::other(e).with_url(info_url))
     }
 
     #[cfg(target_arch = "wasm32")]
-    async fn detect_http(src: &str) -> Result<(String, bool)> {
+    async fn detect_http(&self, ctx: &Context, src: &str) -> Result<(String, bool)> {
         todo!()
     }
 }
 
 #[async_trait]
 impl Detector for BitBucketDetector {
-    async fn detect(&self, src: &str, _: &str) -> Result<(String, bool)> {
+    async fn detect(&self, src: &str, pwd: &str) -> Result<(String, bool)> {
+        self.detect_with(&Context::default(), src, pwd).await
+    }
+
+    async fn detect_with(&self, ctx: &Context, src: &str, _: &str) -> Result<(String, bool)> {
         if src.is_empty() {
             return Ok(("".to_string(), false));
         }
 
         if src.starts_with("bitbucket.org/") {
-            return BitBucketDetector::detect_http(src).await;
+            return self.detect_http(ctx, src).await;
         }
 
         Ok(("".to_string(), false))
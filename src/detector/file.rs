@@ -11,7 +11,7 @@ pub struct FileDetector;
 
 impl FileDetector {
     fn fmt_file_url(path: &str) -> String {
-        return if cfg!(target_family = "windows") {
+        if cfg!(target_family = "windows") {
             // Make sure we're using "/" on Windows. URLs are "/"-based.
             let path = path.replace('\\', "/");
             format!("file://{}", path)
@@ -22,7 +22,7 @@ impl FileDetector {
             } else {
                 format!("file:///{}", path)
             }
-        };
+        }
     }
 }
 
@@ -38,13 +38,15 @@ impl Detector for FileDetector {
         }
 
         if pwd.is_empty() {
-            return Err(error::detector(
+            return Err(error::invalid_source(
                 "relative paths require a module with a pwd".to_string(),
             ));
         }
 
         // Resolve the symlink to it's absolute path
-        let mut abs_path = Path::new(pwd).canonicalize().map_err(error::detector)?;
+        let mut abs_path = Path::new(pwd)
+            .canonicalize()
+            .map_err(error::invalid_source)?;
 
         abs_path.push(src);
 
@@ -60,15 +62,14 @@ impl Detector for FileDetector {
                     continue;
                 }
             }
-            result_path.push(&component);
+            result_path.push(component);
         }
 
         result_path
             .to_str()
-            .ok_or_else(|| error::detector(format!(
-                "could not convert {:?} to a string",
-                result_path
-            )))
+            .ok_or_else(|| {
+                error::invalid_source(format!("could not convert {:?} to a string", result_path))
+            })
             .map(|path| (FileDetector::fmt_file_url(path), true))
     }
 }
@@ -0,0 +1,47 @@
+use super::Detector;
+use crate::{error, Result};
+use async_trait::async_trait;
+use url::Url;
+
+/// GCSDetector implements Detector to detect Google Cloud Storage URLs and
+/// turn them into URLs that the GCS Getter can understand.
+#[derive(Copy, Clone, Debug)]
+pub struct GCSDetector;
+
+impl GCSDetector {
+    fn detect_http(src: &str) -> Result<(String, bool)> {
+        let parts = src.split('/').collect::<Vec<&str>>();
+        if parts.len() < 5 {
+            return Err(error::invalid_source(
+                "URL is not a valid GCS URL".to_string(),
+            ));
+        }
+
+        let version = parts[2];
+        let bucket = parts[3];
+        let object = parts[4..].join("/");
+
+        let url_str = format!(
+            "https://www.googleapis.com/storage/{}/{}/{}",
+            version, bucket, object
+        );
+        let url = Url::parse(&url_str).map_err(error::invalid_source)?;
+
+        Ok((format!("gcs::{}", url.as_str()), true))
+    }
+}
+
+#[async_trait]
+impl Detector for GCSDetector {
+    async fn detect(&self, src: &str, _: &str) -> Result<(String, bool)> {
+        if src.is_empty() {
+            return Ok(("".to_string(), false));
+        }
+
+        if src.contains("googleapis.com/") {
+            return GCSDetector::detect_http(src);
+        }
+
+        Ok(("".to_string(), false))
+    }
+}
@@ -12,13 +12,13 @@ impl GitHubDetector {
     fn detect_http(src: &str) -> Result<(String, bool)> {
         let parts = src.split('/').collect::<Vec<&str>>();
         if parts.len() < 3 {
-            return Err(error::detector(
+            return Err(error::invalid_source(
                 "GitHub URLs should be github.com/username/repo".to_string(),
             ));
         }
 
         let url_str = format!("https://{}", parts[..3].join("/"));
-        let mut url = Url::parse(&url_str).map_err(error::detector)?;
+        let mut url = Url::parse(&url_str).map_err(error::invalid_source)?;
 
         if !url.path().ends_with(".git") {
             url.set_path(&format!("{}.git", url.path()));
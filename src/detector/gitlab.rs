@@ -12,13 +12,13 @@ impl GitLabDetector {
     fn detect_http(src: &str) -> Result<(String, bool)> {
         let parts = src.split('/').collect::<Vec<&str>>();
         if parts.len() < 3 {
-            return Err(error::detector(
+            return Err(error::invalid_source(
                 "GitHub URLs should be gitlab.com/username/repo".to_string(),
             ));
         }
 
         let url_str = format!("https://{}", parts[..3].join("/"));
-        let mut repo_url = Url::parse(&url_str).map_err(error::detector)?;
+        let mut repo_url = Url::parse(&url_str).map_err(error::invalid_source)?;
 
         if !repo_url.path().ends_with(".git") {
             repo_url.set_path(&format!("{}.git", repo_url.path()));
@@ -0,0 +1,32 @@
+use super::Detector;
+use crate::{error, Result};
+use async_trait::async_trait;
+use url::Url;
+
+/// OCIDetector implements Detector to turn OCI references such as
+/// `ghcr.io/org/repo:v1` or `registry:5000/repo@sha256:...` into URLs that
+/// the OCI Getter can understand.
+///
+/// References aren't URLs, but may parse as one with the registry as the
+/// scheme, so this detector is only used when the OCI getter is forced,
+/// e.g. `oci::registry:5000/repo:tag`. Sources that are already URLs are
+/// left alone.
+#[derive(Copy, Clone, Debug)]
+pub struct OCIDetector;
+
+#[async_trait]
+impl Detector for OCIDetector {
+    async fn detect(&self, src: &str, _: &str) -> Result<(String, bool)> {
+        if src.is_empty() {
+            return Ok(("".to_string(), false));
+        }
+
+        if src.contains("://") {
+            return Ok((src.to_string(), true));
+        }
+
+        let url = Url::parse(&format!("oci://{}", src)).map_err(error::invalid_source)?;
+
+        Ok((format!("oci::{}", url), true))
+    }
+}
@@ -0,0 +1,68 @@
+use super::Detector;
+use crate::{error, Result};
+use async_trait::async_trait;
+use url::Url;
+
+/// S3Detector implements Detector to detect S3 URLs and turn them into
+/// URLs that the S3 Getter can understand.
+///
+/// Both the path style (`s3.amazonaws.com/bucket/key`) and the virtual
+/// hosted style (`bucket.s3.amazonaws.com/key`) are detected, with or
+/// without a region, and turned into path style URLs.
+#[derive(Copy, Clone, Debug)]
+pub struct S3Detector;
+
+impl S3Detector {
+    fn detect_http(src: &str) -> Result<(String, bool)> {
+        let parts = src.split('/').collect::<Vec<&str>>();
+        if parts.len() < 2 {
+            return Err(error::invalid_source(
+                "URL is not a valid S3 URL".to_string(),
+            ));
+        }
+
+        let host_parts = parts[0].split('.').collect::<Vec<&str>>();
+        let url_str = match host_parts.as_slice() {
+            // s3.amazonaws.com/bucket/key or s3-eu-west-1.amazonaws.com/bucket/key
+            [region, _, _] => format!("https://{}.amazonaws.com/{}", region, parts[1..].join("/")),
+            // bucket.s3.amazonaws.com/key or bucket.s3-eu-west-1.amazonaws.com/key
+            [bucket, region, _, _] => format!(
+                "https://{}.amazonaws.com/{}/{}",
+                region,
+                bucket,
+                parts[1..].join("/")
+            ),
+            // bucket.s3.eu-west-1.amazonaws.com/key
+            [bucket, "s3", region, _, _] => format!(
+                "https://s3.{}.amazonaws.com/{}/{}",
+                region,
+                bucket,
+                parts[1..].join("/")
+            ),
+            _ => {
+                return Err(error::invalid_source(
+                    "URL is not a valid S3 URL".to_string(),
+                ))
+            }
+        };
+
+        let url = Url::parse(&url_str).map_err(error::invalid_source)?;
+
+        Ok((format!("s3::{}", url.as_str()), true))
+    }
+}
+
+#[async_trait]
+impl Detector for S3Detector {
+    async fn detect(&self, src: &str, _: &str) -> Result<(String, bool)> {
+        if src.is_empty() {
+            return Ok(("".to_string(), false));
+        }
+
+        if src.contains(".amazonaws.com/") {
+            return S3Detector::detect_http(src);
+        }
+
+        Ok(("".to_string(), false))
+    }
+}
@@ -31,9 +31,10 @@ pub(crate) fn detect_ssh(src: &str) -> Result<Option<Url>> {
         path.len()
     };
 
-    let mut url = Url::parse(&format!("ssh://{}", host)).map_err(error::detector)?;
+    let mut url = Url::parse(&format!("ssh://{}", host)).map_err(error::invalid_source)?;
 
-    url.set_username(user).map_err(|_| error::detector("error setting username"))?;
+    url.set_username(user)
+        .map_err(|_| error::invalid_source("error setting username"))?;
 
     url.set_path(&path[..qidx]);
 
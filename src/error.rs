@@ -1,26 +1,56 @@
 #![cfg_attr(target_arch = "wasm32", allow(unused))]
 use std::error::Error as StdError;
 use std::fmt;
+use std::time::Duration;
 use url::Url;
 
-pub(crate) type Result<T> = core::result::Result<T, Error>;
+/// A `Result` alias where the `Err` case is `getter::Error`.
+pub type Result<T> = core::result::Result<T, Error>;
 
 // pub(crate) trait StdError: fmt::Display + fmt::Debug + Send + Sync {
 //     fn source(&self) -> Option<&(dyn StdError + 'static)>;
 // }
 
-pub(crate) type BoxError = Box<dyn StdError>;
+pub(crate) type BoxError = Box<dyn StdError + Send + Sync>;
 
-#[derive(Debug)]
-pub(crate) enum Kind {
-    Detector,
-    Getter,
+/// ErrorKind describes what went wrong, so that callers can react to
+/// specific failures without inspecting the error message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The source string, URL or one of its parameters is invalid.
+    InvalidSource,
+    /// No getter is registered for the scheme of the source.
+    UnsupportedScheme,
+    /// The source, or a part of it such as a subdir or ref, doesn't exist.
+    NotFound,
+    /// The credentials used to access the source were rejected.
+    Authentication,
+    /// The downloaded file doesn't match the expected checksum.
+    ChecksumMismatch,
+    /// The server answered with an unsuccessful HTTP status code. 401 and
+    /// 403 are reported as Authentication and 404 as NotFound instead.
+    HttpStatus(u16),
+    /// Reading or writing local files failed.
+    Io,
+    /// The downloaded archive couldn't be unpacked.
+    Decompression,
+    /// The connection to the server failed.
+    Network,
+    /// The server stopped responding, or the download took longer than
+    /// allowed.
+    Timeout,
+    /// The download was cancelled.
+    Cancelled,
+    /// Any other error.
+    Other,
 }
 
 struct Inner {
-    kind: Kind,
+    kind: ErrorKind,
     source: Option<BoxError>,
     url: Option<Url>,
+    retry_after: Option<Duration>,
 }
 
 /// The Errors that may occur when processing.
@@ -29,7 +59,7 @@ pub struct Error {
 }
 
 impl Error {
-    pub(crate) fn new<E>(kind: Kind, source: Option<E>) -> Error
+    pub(crate) fn new<E>(kind: ErrorKind, source: Option<E>) -> Error
     where
         E: Into<BoxError>,
     {
@@ -38,13 +68,86 @@ impl Error {
                 kind,
                 source: source.map(Into::into),
                 url: None,
+                retry_after: None,
             }),
         }
     }
 
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.inner.kind
+    }
+
+    /// Returns a possible URL related to this error.
     pub fn url(&self) -> Option<&Url> {
         self.inner.url.as_ref()
     }
+
+    /// Returns whether the operation that failed may succeed if it is
+    /// simply tried again, e.g. after a dropped or stalled connection, a
+    /// server error or when being rate limited.
+    pub fn is_retryable(&self) -> bool {
+        match self.inner.kind {
+            ErrorKind::Network | ErrorKind::Timeout => true,
+            ErrorKind::HttpStatus(code) => code == 408 || code == 429 || code >= 500,
+            _ => false,
+        }
+    }
+
+    /// Returns how long the server asked us to wait before trying again,
+    /// if it did.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.inner.retry_after
+    }
+
+    // with_retry_after records the delay the server asked for before the
+    // next attempt.
+    pub(crate) fn with_retry_after(mut self, retry_after: Option<Duration>) -> Error {
+        self.inner.retry_after = retry_after;
+        self
+    }
+
+    // with_url records the URL that was being processed when this error
+    // occurred, unless a more specific one has been recorded already.
+    //
+    // Credentials in the URL are redacted, since errors end up in logs.
+    pub(crate) fn with_url(mut self, url: &Url) -> Error {
+        if self.inner.url.is_none() {
+            self.inner.url = Some(redact(url));
+        }
+        self
+    }
+}
+
+// redact returns a copy of url with its password and any secrets in the
+// query string replaced.
+fn redact(url: &Url) -> Url {
+    const SECRET_PARAMS: &[&str] = &["sshkey", "aws_access_key_secret", "aws_access_key_token"];
+
+    let mut url = url.clone();
+    if url.password().is_some() {
+        let _ = url.set_password(Some("redacted"));
+    }
+
+    if url
+        .query_pairs()
+        .any(|(k, _)| SECRET_PARAMS.contains(&k.as_ref()))
+    {
+        let query: Vec<_> = url
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if SECRET_PARAMS.contains(&k.as_ref()) {
+                    "redacted".into()
+                } else {
+                    v
+                };
+                (k.into_owned(), v.into_owned())
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+
+    url
 }
 
 impl fmt::Debug for Error {
@@ -79,8 +182,18 @@ impl fmt::Display for Error {
         }
 
         match self.inner.kind {
-            Kind::Detector => f.write_str("detector error")?,
-            Kind::Getter => f.write_str("getter error")?,
+            ErrorKind::InvalidSource => f.write_str("invalid source")?,
+            ErrorKind::UnsupportedScheme => f.write_str("unsupported scheme")?,
+            ErrorKind::NotFound => f.write_str("not found")?,
+            ErrorKind::Authentication => f.write_str("authentication failed")?,
+            ErrorKind::ChecksumMismatch => f.write_str("checksum mismatch")?,
+            ErrorKind::HttpStatus(code) => write!(f, "bad response code: {}", code)?,
+            ErrorKind::Io => f.write_str("i/o error")?,
+            ErrorKind::Decompression => f.write_str("decompression error")?,
+            ErrorKind::Network => f.write_str("network error")?,
+            ErrorKind::Timeout => f.write_str("timed out")?,
+            ErrorKind::Cancelled => f.write_str("cancelled")?,
+            ErrorKind::Other => f.write_str("error")?,
         };
 
         ForUrl(self.inner.url.as_ref()).fmt(f)?;
@@ -115,10 +228,63 @@ impl From<crate::error::Error> for js_sys::Error {
 
 // constructors
 
-pub(crate) fn detector<E: Into<BoxError>>(e: E) -> Error {
-    Error::new(Kind::Detector, Some(e))
+pub(crate) fn invalid_source<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::InvalidSource, Some(e))
+}
+
+pub(crate) fn unsupported_scheme<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::UnsupportedScheme, Some(e))
+}
+
+pub(crate) fn not_found<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::NotFound, Some(e))
+}
+
+pub(crate) fn authentication<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::Authentication, Some(e))
+}
+
+pub(crate) fn checksum_mismatch<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::ChecksumMismatch, Some(e))
+}
+
+// status returns the error for an unsuccessful HTTP status code, with the
+// message the server sent along if there is one. Codes with a kind of their
+// own get it whichever server they come from: 401 and 403 reject the
+// credentials and 404 means that the source doesn't exist.
+pub(crate) fn status(code: u16, message: Option<String>) -> Error {
+    let kind = match code {
+        401 | 403 => ErrorKind::Authentication,
+        404 => ErrorKind::NotFound,
+        _ => ErrorKind::HttpStatus(code),
+    };
+    let message = match kind {
+        ErrorKind::HttpStatus(_) => message,
+        _ => message.or_else(|| Some(format!("bad response code: {}", code))),
+    };
+    Error::new(kind, message)
+}
+
+pub(crate) fn io<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::Io, Some(e))
+}
+
+pub(crate) fn decompression<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::Decompression, Some(e))
+}
+
+pub(crate) fn network<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::Network, Some(e))
+}
+
+pub(crate) fn timeout<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::Timeout, Some(e))
+}
+
+pub(crate) fn cancelled() -> Error {
+    Error::new(ErrorKind::Cancelled, None::<BoxError>)
 }
 
-pub(crate) fn getter<E: Into<BoxError>>(e: E) -> Error {
-    Error::new(Kind::Getter, Some(e))
+pub(crate) fn other<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(ErrorKind::Other, Some(e))
 }
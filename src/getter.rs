@@ -1,31 +1,142 @@
-use crate::{ClientMode, Result};
+//! Getters download sources that have been turned into proper URLs by the
+//! detectors.
+
+use crate::{error, ClientMode, ProgressListener, Result};
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures_util::Stream;
 use std::collections::BTreeMap;
 use std::fmt;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
 use std::sync::Arc;
+use url::Url;
 
 mod http;
-pub(crate) use http::HttpGetter;
+pub use http::HttpGetter;
+
+cfg_if::cfg_if! {
+    if #[cfg(not(target_arch = "wasm32"))] {
+        mod command;
+
+        mod file;
+        pub use file::FileGetter;
+
+        mod gcs;
+        pub use gcs::GCSGetter;
 
-mod git;
-pub(crate) use git::GitGetter;
+        mod git;
+        pub use git::GitGetter;
+
+        mod hg;
+        pub use hg::HgGetter;
+
+        mod oci;
+        pub use oci::OCIGetter;
+
+        mod s3;
+        pub use s3::S3Getter;
+    }
+}
 
 lazy_static::lazy_static! {
     #[allow(missing_docs)]
     pub static ref GETTERS: Arc<BTreeMap<&'static str, Box<dyn Getter>>> = {
         let mut map: BTreeMap<&str, Box<dyn Getter>> = BTreeMap::new();
 
+        #[cfg(not(target_arch = "wasm32"))]
+        map.insert("file", Box::new(FileGetter::default()));
+        #[cfg(not(target_arch = "wasm32"))]
+        map.insert("gcs", Box::new(GCSGetter::default()));
+        #[cfg(not(target_arch = "wasm32"))]
         map.insert("git", Box::new(GitGetter));
-        map.insert("http", Box::new(HttpGetter));
-        map.insert("https", Box::new(HttpGetter));
+        #[cfg(not(target_arch = "wasm32"))]
+        map.insert("hg", Box::new(HgGetter));
+        map.insert("http", Box::new(HttpGetter::default()));
+        map.insert("https", Box::new(HttpGetter::default()));
+        #[cfg(not(target_arch = "wasm32"))]
+        map.insert("oci", Box::new(OCIGetter::default()));
+        #[cfg(not(target_arch = "wasm32"))]
+        map.insert("s3", Box::new(S3Getter::default()));
 
         Arc::new(map)
     };
 }
 
-// Getter defines the interface that schemes must implement to download
-// things.
+/// Context carries the settings of a single download that the client hands
+/// to each getter.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct Context {
+    /// Progress is notified about the files the getter downloads, if set.
+    pub progress: Option<Arc<dyn ProgressListener>>,
+
+    /// Timeouts limits how long connecting to and waiting for servers may
+    /// take. Its total timeout is enforced through `deadline` instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub timeouts: crate::Timeouts,
+
+    /// Deadline is the time by which the download must be complete, if
+    /// any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub deadline: Option<std::time::Instant>,
+
+    /// Cancel aborts the download when it is cancelled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub cancel: crate::CancellationToken,
+
+    /// Netrc is the path of the .netrc file that HTTP credentials are looked
+    /// up in. When unset, `$NETRC` or `~/.netrc` is used if it exists.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub netrc: Option<std::path::PathBuf>,
+
+    // client is the client the download was started with, whose detectors
+    // and getters are used for sources that a getter is redirected to.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) client: Option<Arc<crate::Client>>,
+
+    // redirects are the URLs that redirected to other sources on the way to
+    // the one being downloaded, to detect redirect loops.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) redirects: Vec<Url>,
+
+    // checksum is the checksum of the file being downloaded, if any.
+    // Getters check the file against it before moving it into place, so
+    // that a bad file never ends up at the destination, see checksum::copy.
+    // The client verifies the file afterwards if they didn't.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) checksum: Option<Arc<crate::checksum::FileChecksum>>,
+
+    // clients are the HTTP clients that the requests of the download are
+    // sent with.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) clients: crate::tls::Clients,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Context {
+    // run runs fut until it completes, the deadline of this context passes
+    // or it is cancelled. fut is dropped in the latter cases, so it must
+    // clean up after itself when dropped.
+    pub(crate) async fn run<T>(
+        &self,
+        fut: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        crate::timeout::guard(self.deadline, &self.cancel, fut).await
+    }
+}
+
+/// ByteStream is an owned stream of the bytes of a single remote file, as
+/// returned by [Getter::stream].
+pub type ByteStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send + 'static>>;
+
+/// Getter defines the interface that schemes must implement to download
+/// things.
+///
+/// Every call is given the [Context] of the download it is part of, which
+/// getters should honour, e.g. by reporting their progress to it and by
+/// enforcing its timeouts.
 #[async_trait]
 pub trait Getter: fmt::Debug + Sync + Send + 'static {
     /// Get downloads the given URL into the given directory. This always
@@ -34,19 +145,47 @@ pub trait Getter: fmt::Debug + Sync + Send + 'static {
     /// The directory may already exist (if we're updating). If it is in a
     /// format that isn't understood, an error should be returned. Get shouldn't
     /// simply nuke the directory.
-    async fn get(&self, url: &str) -> Result<&dyn Stream<Item = &[u8]>>;
+    async fn get(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()>;
 
     /// get_file downloads the give URL into the given path. The URL must
     /// reference a single file. If possible, the Getter should check if
     /// the remote end contains the same file and no-op this operation.
-    async fn get_file(&self, url: &str) -> Result<&dyn Stream<Item = &[u8]>>;
+    async fn get_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()>;
 
     /// client_mode returns the mode based on the given URL. This is used to
     /// allow clients to let the getters decide which mode to use.
-    fn client_mode(&self, url: &str) -> Result<ClientMode>;
+    fn client_mode(&self, url: &Url) -> Result<ClientMode>;
+
+    /// stream returns the contents of the single file referenced by the
+    /// given URL as an owned stream of bytes, for consumers that want the
+    /// data in memory rather than on disk.
+    ///
+    /// Getters that can't stream return an error, which is the default.
+    async fn stream(&self, _ctx: &Context, url: &Url) -> Result<ByteStream> {
+        Err(error::unsupported_scheme(format!(
+            "streaming is not supported for url ({})",
+            url
+        )))
+    }
+}
+
+// join_key returns the path of the object with the given relative key below
+// dst, making sure that it doesn't end up outside of it.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn join_key(dst: &Path, key: &str) -> Result<std::path::PathBuf> {
+    let mut path = dst.to_path_buf();
+    for component in Path::new(key).components() {
+        match component {
+            std::path::Component::Normal(name) => path.push(name),
+            std::path::Component::CurDir => {}
+            _ => {
+                return Err(error::invalid_source(format!(
+                    "object key '{}' points outside of the destination",
+                    key
+                )))
+            }
+        }
+    }
 
-    // set_client allows a getter to know it's client
-    // in order to access client's Get functions or
-    // progress tracking.
-    // fn set_client(&mut self, client: Client);
+    Ok(path)
 }
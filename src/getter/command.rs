@@ -0,0 +1,53 @@
+use crate::{error, Result};
+use tokio::process::Command;
+
+// Tool is a version control tool whose binary the getters run, along with
+// the messages that its human-readable output contains for the most common
+// failures, since that is the only place it tells us what went wrong.
+pub(crate) struct Tool {
+    pub(crate) name: &'static str,
+    pub(crate) authentication: &'static [&'static str],
+    pub(crate) not_found: &'static [&'static str],
+    pub(crate) timeout: &'static [&'static str],
+}
+
+impl Tool {
+    // run runs the given command of this tool and turns a failure into an
+    // error that includes its output.
+    //
+    // The tool is killed if the returned future is dropped, e.g. when the
+    // download is cancelled.
+    pub(crate) async fn run(&self, cmd: &mut Command) -> Result<()> {
+        let output = cmd.kill_on_drop(true).output().await.map_err(|e| {
+            error::other(format!(
+                "{} must be available and on the PATH: {}",
+                self.name, e
+            ))
+        })?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = format!(
+            "{} exited with {}: {}{}",
+            self.name,
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            stderr
+        );
+
+        let contains = |messages: &[&str]| messages.iter().any(|s| stderr.contains(s));
+        if contains(self.authentication) {
+            return Err(error::authentication(message));
+        }
+        if contains(self.not_found) {
+            return Err(error::not_found(message));
+        }
+        if contains(self.timeout) {
+            return Err(error::timeout(message));
+        }
+        Err(error::other(message))
+    }
+}
@@ -0,0 +1,164 @@
+use super::{Context, Getter};
+use crate::copy_dir::copy_dir;
+use crate::progress::FileProgress;
+use crate::{checksum, error, ClientMode, Result};
+use async_trait::async_trait;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// FileGetter is a Getter implementation that will download a module from
+/// a file scheme.
+///
+/// Directories are symlinked into place by default, which is fast and keeps
+/// the destination up to date with the source. Set `copy` to copy them
+/// instead. Single files are always copied.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FileGetter {
+    /// Copy, if set to true, will copy directories instead of symlinking
+    /// them.
+    pub copy: bool,
+}
+
+impl FileGetter {
+    // get_dir symlinks or copies the directory at url to dst.
+    async fn get_dir(&self, dst: &Path, url: &Url) -> Result<()> {
+        let (path, metadata) = source(url)?;
+
+        // The source path must be a directory to be usable.
+        if !metadata.is_dir() {
+            return Err(error::invalid_source(format!(
+                "source path must be a directory ({})",
+                path.display()
+            )));
+        }
+
+        // If the destination already exists, it must be a symlink, which we
+        // replace. Anything else might be someone's data.
+        if let Ok(metadata) = fs::symlink_metadata(dst) {
+            if !metadata.file_type().is_symlink() {
+                return Err(error::other(format!(
+                    "destination exists and is not a symlink ({})",
+                    dst.display()
+                )));
+            }
+            remove_symlink(dst)?;
+        }
+
+        // Create all the parent directories
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).map_err(error::io)?;
+        }
+
+        if !self.copy {
+            return symlink_dir(&path, dst);
+        }
+
+        fs::create_dir(dst).map_err(error::io)?;
+        let dst = dst.to_path_buf();
+        tokio::task::spawn_blocking(move || copy_dir(&dst, &path, false))
+            .await
+            .map_err(error::other)?
+            .map_err(error::io)
+    }
+
+    // copy_file copies the single file at url to dst.
+    async fn copy_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let (path, metadata) = source(url)?;
+
+        // The source path must be a file to be usable.
+        if metadata.is_dir() {
+            return Err(error::invalid_source(format!(
+                "source path must be a file ({})",
+                path.display()
+            )));
+        }
+
+        // If the destination is a symlink, remove it rather than writing
+        // through it, which could end up overwriting the file it points to.
+        if let Ok(metadata) = fs::symlink_metadata(dst) {
+            if metadata.file_type().is_symlink() {
+                remove_symlink(dst)?;
+            }
+        }
+
+        // Create all the parent directories
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).map_err(error::io)?;
+        }
+
+        let mut progress =
+            FileProgress::start(ctx.progress.as_deref(), url.as_str(), Some(metadata.len()));
+        let copied = checksum::copy(ctx.checksum.as_deref(), &path, dst).await?;
+        progress.add(copied);
+        progress.finish();
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Getter for FileGetter {
+    async fn get(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.get_dir(dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
+    }
+
+    async fn get_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.copy_file(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
+    }
+
+    fn client_mode(&self, url: &Url) -> Result<ClientMode> {
+        let (_, metadata) = source(url).map_err(|e| e.with_url(url))?;
+
+        if metadata.is_dir() {
+            Ok(ClientMode::Dir)
+        } else {
+            Ok(ClientMode::File)
+        }
+    }
+}
+
+// source returns the local path referenced by the file URL, along with its
+// metadata. The path must exist.
+fn source(url: &Url) -> Result<(PathBuf, fs::Metadata)> {
+    let path = url
+        .to_file_path()
+        .map_err(|_| error::invalid_source("invalid file url"))?;
+
+    match fs::metadata(&path) {
+        Ok(metadata) => Ok((path, metadata)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(error::not_found(format!(
+            "source path doesn't exist ({})",
+            path.display()
+        ))),
+        Err(e) => Err(error::io(format!(
+            "source path error ({}): {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+fn symlink_dir(src: &Path, dst: &Path) -> Result<()> {
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(src, dst);
+    #[cfg(windows)]
+    let result = std::os::windows::fs::symlink_dir(src, dst);
+
+    result.map_err(error::io)
+}
+
+fn remove_symlink(path: &Path) -> Result<()> {
+    // On Windows, directory symlinks have to be removed as directories.
+    #[cfg(windows)]
+    if fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false) {
+        return fs::remove_dir(path).map_err(error::io);
+    }
+
+    fs::remove_file(path).map_err(error::io)
+}
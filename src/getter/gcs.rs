@@ -0,0 +1,467 @@
+use super::http::{read_body, request, save};
+use super::{join_key, Context, Getter};
+use crate::{error, ClientMode, Error, Result};
+use async_trait::async_trait;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::{Body, Request, Response};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::RsaPrivateKey;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// GCSGetter is a Getter implementation that will download from a Google
+/// Cloud Storage bucket.
+///
+/// URLs have the form `https://www.googleapis.com/storage/v1/bucket/path`.
+/// A single object is downloaded in file mode, and every object below the
+/// path in dir mode. The `generation` query parameter selects a generation
+/// of the object.
+///
+/// Requests are authorized with `access_token`, or with a token obtained
+/// for the service account in `credentials_file`. If neither is set, the
+/// `GOOGLE_OAUTH_ACCESS_TOKEN` and `GOOGLE_APPLICATION_CREDENTIALS`
+/// environment variables are used the same way, in that order. Requests are
+/// sent without authorization if there is none of these.
+#[derive(Clone, Default)]
+pub struct GCSGetter {
+    /// Tls configures how HTTPS connections are verified.
+    pub tls: crate::TlsConfig,
+
+    /// Endpoint is the base URL of the JSON API to send requests to instead
+    /// of the host of the URL, e.g. `http://localhost:4443` for a local
+    /// fake-gcs-server. The `STORAGE_EMULATOR_HOST` environment variable is
+    /// used if it isn't set.
+    pub endpoint: Option<Url>,
+
+    /// AccessToken is an OAuth 2.0 access token to authorize requests with.
+    pub access_token: Option<String>,
+
+    /// CredentialsFile is the path to the JSON key file of a service
+    /// account to authorize requests as.
+    pub credentials_file: Option<PathBuf>,
+}
+
+impl fmt::Debug for GCSGetter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GCSGetter")
+            .field("tls", &self.tls)
+            .field("endpoint", &self.endpoint)
+            .field(
+                "access_token",
+                &self.access_token.as_ref().map(|_| "redacted"),
+            )
+            .field("credentials_file", &self.credentials_file)
+            .finish()
+    }
+}
+
+// Location is where a URL points to within GCS.
+struct Location {
+    // api is the base URL of the JSON API, including its version.
+    api: Url,
+    bucket: String,
+    object: String,
+    generation: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Objects {
+    #[serde(default)]
+    items: Vec<Object>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Object {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ServiceAccount {
+    #[serde(rename = "type")]
+    kind: String,
+    client_email: String,
+    private_key: String,
+    private_key_id: Option<String>,
+    token_uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Token {
+    access_token: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct GCSError {
+    error: GCSErrorDetails,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct GCSErrorDetails {
+    message: String,
+}
+
+// TOKEN_URI is where access tokens are obtained for service accounts whose
+// key file doesn't name another place.
+const TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+// SCOPE is the OAuth 2.0 scope requested for service accounts.
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+
+impl GCSGetter {
+    // endpoint returns the configured endpoint, if any.
+    fn endpoint(&self) -> Option<Url> {
+        if let Some(ref endpoint) = self.endpoint {
+            return Some(endpoint.clone());
+        }
+
+        // Like the Google Cloud client libraries, accept the emulator host
+        // with or without a scheme.
+        let host = env::var("STORAGE_EMULATOR_HOST")
+            .ok()
+            .filter(|h| !h.is_empty())?;
+        if host.contains("://") {
+            Url::parse(&host).ok()
+        } else {
+            Url::parse(&format!("http://{}", host)).ok()
+        }
+    }
+
+    // get_object downloads the object with the given name into dst.
+    async fn get_object(
+        &self,
+        ctx: &Context,
+        location: &Location,
+        token: Option<&str>,
+        name: &str,
+        generation: Option<&str>,
+        dst: &Path,
+    ) -> Result<()> {
+        let mut url = location.api.clone();
+        url.path_segments_mut()
+            .map_err(|_| error::invalid_source("gcs endpoint can't be a base"))?
+            .pop_if_empty()
+            .extend(["b", &location.bucket, "o", name]);
+        url.query_pairs_mut().append_pair("alt", "media");
+        if let Some(generation) = generation {
+            url.query_pairs_mut().append_pair("generation", generation);
+        }
+
+        let resp = self.send(ctx, &url, token).await?;
+        let name = format!("gs://{}/{}", location.bucket, name);
+        save(ctx, resp, dst, &name).await
+    }
+
+    // get_prefix downloads every object below the path the URL points to
+    // into dst, keeping the paths relative to that path.
+    async fn get_prefix(
+        &self,
+        ctx: &Context,
+        dst: &Path,
+        location: &Location,
+        token: Option<&str>,
+    ) -> Result<()> {
+        let mut prefix = location.object.clone();
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+
+        tokio::fs::create_dir_all(dst).await.map_err(error::io)?;
+
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = location.api.clone();
+            url.path_segments_mut()
+                .map_err(|_| error::invalid_source("gcs endpoint can't be a base"))?
+                .pop_if_empty()
+                .extend(["b", &location.bucket, "o"]);
+            url.query_pairs_mut().append_pair("prefix", &prefix);
+            if let Some(page) = page_token.take() {
+                url.query_pairs_mut().append_pair("pageToken", &page);
+            }
+
+            let resp = self.send(ctx, &url, token).await?;
+            let body = read_body(ctx, resp).await?;
+            let objects: Objects = serde_json::from_str(&body)
+                .map_err(|e| error::other(format!("invalid object listing: {}", e)))?;
+
+            for object in objects.items {
+                let path = match object.name.strip_prefix(&prefix) {
+                    // Names ending in a slash are placeholders for empty
+                    // directories.
+                    Some(path) if !path.is_empty() && !path.ends_with('/') => path,
+                    _ => continue,
+                };
+
+                let object_dst = join_key(dst, path)?;
+                self.get_object(ctx, location, token, &object.name, None, &object_dst)
+                    .await?;
+            }
+
+            match objects.next_page_token {
+                Some(page) if !page.is_empty() => page_token = Some(page),
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    // send sends a GET request for url, authorized with token if there is
+    // one, and returns the response if it was successful.
+    async fn send(&self, ctx: &Context, url: &Url, token: Option<&str>) -> Result<Response<Body>> {
+        let mut req = Request::get(url.as_str());
+        if let Some(token) = token {
+            req = req.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        let req = req.body(Body::empty()).map_err(error::invalid_source)?;
+        let resp = request(&self.tls, ctx, req).await?;
+
+        if !resp.status().is_success() {
+            return Err(status_error(ctx, resp).await);
+        }
+
+        Ok(resp)
+    }
+
+    // token returns the access token to authorize requests with, if any.
+    // The settings of the getter take precedence over the environment.
+    async fn token(&self, ctx: &Context) -> Result<Option<String>> {
+        if let Some(ref token) = self.access_token {
+            return Ok(Some(token.clone()));
+        }
+        if let Some(ref path) = self.credentials_file {
+            return self.service_account_token(ctx, path).await.map(Some);
+        }
+        if let Some(token) = env::var("GOOGLE_OAUTH_ACCESS_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty())
+        {
+            return Ok(Some(token));
+        }
+
+        let path = match env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => return Ok(None),
+        };
+
+        self.service_account_token(ctx, &path).await.map(Some)
+    }
+
+    // service_account_token obtains an access token for the service account
+    // whose key file is at path, by exchanging a JWT signed with its key.
+    async fn service_account_token(&self, ctx: &Context, path: &Path) -> Result<String> {
+        let contents = fs::read_to_string(path).map_err(error::io)?;
+        let account: ServiceAccount = serde_json::from_str(&contents).map_err(|e| {
+            error::authentication(format!(
+                "invalid credentials file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        if account.kind != "service_account" {
+            return Err(error::authentication(format!(
+                "credentials file {} is of type '{}', only service accounts are supported",
+                path.display(),
+                account.kind
+            )));
+        }
+
+        let token_uri = account.token_uri.as_deref().unwrap_or(TOKEN_URI);
+        let assertion = jwt(&account, token_uri)?;
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer")
+            .append_pair("assertion", &assertion)
+            .finish();
+
+        let req = Request::post(token_uri)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .map_err(error::invalid_source)?;
+        let resp = request(&self.tls, ctx, req).await?;
+
+        let status = resp.status();
+        let body = read_body(ctx, resp).await?;
+        if !status.is_success() {
+            return Err(error::authentication(format!(
+                "obtaining an access token for {} failed ({}): {}",
+                account.client_email, status, body
+            )));
+        }
+
+        let token: Token = serde_json::from_str(&body)
+            .map_err(|e| error::authentication(format!("invalid access token: {}", e)))?;
+
+        Ok(token.access_token)
+    }
+}
+
+#[async_trait]
+impl Getter for GCSGetter {
+    async fn get(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let location =
+            Location::parse(url, self.endpoint().as_ref()).map_err(|e| e.with_url(url))?;
+
+        ctx.run(async {
+            let token = self.token(ctx).await?;
+            self.get_prefix(ctx, dst, &location, token.as_deref()).await
+        })
+        .await
+        .map_err(|e| e.with_url(url))
+    }
+
+    async fn get_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let location =
+            Location::parse(url, self.endpoint().as_ref()).map_err(|e| e.with_url(url))?;
+        if location.object.is_empty() {
+            return Err(error::invalid_source("no object in gcs url").with_url(url));
+        }
+
+        ctx.run(async {
+            let token = self.token(ctx).await?;
+            let name = &location.object;
+            let generation = location.generation.as_deref();
+            self.get_object(ctx, &location, token.as_deref(), name, generation, dst)
+                .await
+        })
+        .await
+        .map_err(|e| e.with_url(url))
+    }
+
+    fn client_mode(&self, url: &Url) -> Result<ClientMode> {
+        if url.path().ends_with('/') {
+            Ok(ClientMode::Dir)
+        } else {
+            Ok(ClientMode::File)
+        }
+    }
+}
+
+impl Location {
+    // parse works out the bucket and object that url points to, and where
+    // to send requests for them.
+    fn parse(url: &Url, endpoint: Option<&Url>) -> Result<Location> {
+        let segments: Vec<String> = url
+            .path_segments()
+            .map(|segments| {
+                segments
+                    .map(|s| {
+                        percent_encoding::percent_decode_str(s)
+                            .decode_utf8_lossy()
+                            .into_owned()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let (version, bucket, object) = match segments.as_slice() {
+            [storage, version, bucket, object @ ..] if storage == "storage" && !bucket.is_empty() => {
+                (version, bucket.clone(), object.join("/"))
+            }
+            _ => {
+                return Err(error::invalid_source(
+                    "URL is not a valid GCS URL, it should be https://www.googleapis.com/storage/v1/bucket/path",
+                ))
+            }
+        };
+
+        let mut api = match endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => {
+                let mut api = url.clone();
+                api.set_path("");
+                api
+            }
+        };
+        api.set_query(None);
+        api.set_fragment(None);
+        api.path_segments_mut()
+            .map_err(|_| error::invalid_source("gcs endpoint can't be a base"))?
+            .pop_if_empty()
+            .extend(["storage", version]);
+
+        let generation = url
+            .query_pairs()
+            .find(|(key, _)| key == "generation")
+            .map(|(_, value)| value.into_owned())
+            .filter(|generation| !generation.is_empty());
+
+        Ok(Location {
+            api,
+            bucket,
+            object,
+            generation,
+        })
+    }
+}
+
+// jwt returns a JWT asserting the identity of the service account, signed
+// with its private key, to exchange for an access token at token_uri.
+fn jwt(account: &ServiceAccount, token_uri: &str) -> Result<String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+    if let Some(ref kid) = account.private_key_id {
+        header["kid"] = serde_json::Value::from(kid.as_str());
+    }
+    let claims = serde_json::json!({
+        "iss": account.client_email,
+        "scope": SCOPE,
+        "aud": token_uri,
+        "iat": now,
+        "exp": now + 3600,
+    });
+
+    let message = format!(
+        "{}.{}",
+        base64::encode_config(header.to_string(), base64::URL_SAFE_NO_PAD),
+        base64::encode_config(claims.to_string(), base64::URL_SAFE_NO_PAD)
+    );
+
+    // Service account keys are PKCS#8, but older tools write PKCS#1.
+    let key = RsaPrivateKey::from_pkcs8_pem(&account.private_key)
+        .or_else(|e| RsaPrivateKey::from_pkcs1_pem(&account.private_key).map_err(|_| e))
+        .map_err(|e| error::authentication(format!("invalid service account key: {}", e)))?;
+    let signature = SigningKey::<Sha256>::new(key)
+        .try_sign(message.as_bytes())
+        .map_err(error::other)?
+        .to_vec();
+
+    Ok(format!(
+        "{}.{}",
+        message,
+        base64::encode_config(signature, base64::URL_SAFE_NO_PAD)
+    ))
+}
+
+// status_error turns an unsuccessful response into an error, including the
+// reason GCS gave for it.
+async fn status_error(ctx: &Context, resp: Response<Body>) -> Error {
+    let status = resp.status().as_u16();
+    let retry_after = crate::retry::retry_after(resp.headers());
+
+    let message = match read_body(ctx, resp).await {
+        Ok(body) => match serde_json::from_str::<GCSError>(&body) {
+            Ok(reason) if !reason.error.message.is_empty() => reason.error.message,
+            _ => body.trim().to_string(),
+        },
+        Err(_) => String::new(),
+    };
+    let message = format!("{}: {}", status, message);
+
+    error::status(status, Some(message)).with_retry_after(retry_after)
+}
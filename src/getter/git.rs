@@ -1,22 +1,483 @@
-use super::Getter;
-use crate::{ClientMode, Result};
+use super::command::Tool;
+use super::{Context, Getter};
+use crate::progress::FileProgress;
+use crate::{checksum, error, ClientMode, Error, Result};
 use async_trait::async_trait;
-use futures_util::Stream;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+use tokio::process::Command;
+use url::Url;
 
+/// GitGetter is a Getter implementation that will download a module from
+/// a git repository.
+///
+/// The system `git` binary is used, so it must be available on the PATH.
+/// The following query parameters are understood:
+///
+/// * `ref` is the branch, tag or commit to check out.
+/// * `depth` creates a shallow clone with that many commits. `ref` must be
+///   a branch or tag name when it is set.
+/// * `sshkey` is a base64 encoded private key that is used to authenticate
+///   SSH sources.
+///
+/// HTTP(S) sources without credentials in the URL are authenticated with
+/// those for their host in the .netrc file, see [Context::netrc].
 #[derive(Copy, Clone, Debug)]
-pub(crate) struct GitGetter;
+pub struct GitGetter;
+
+impl GitGetter {
+    async fn clone(
+        &self,
+        ctx: &Context,
+        dst: &Path,
+        creds: &Credentials<'_>,
+        url: &Url,
+        git_ref: &str,
+        depth: usize,
+    ) -> Result<()> {
+        let mut cmd = git_command(ctx, creds);
+        cmd.arg("clone");
+
+        if depth > 0 {
+            // We handle an unspecified ref differently than explicitly
+            // selecting the default branch.
+            let branch = if git_ref.is_empty() {
+                find_remote_default_branch(ctx, creds, url).await
+            } else {
+                git_ref.to_string()
+            };
+            check_ref(&branch)?;
+
+            cmd.arg("--depth")
+                .arg(depth.to_string())
+                .arg("--branch")
+                .arg(branch);
+        }
+
+        cmd.arg("--").arg(url.as_str()).arg(dst);
+
+        if let Err(e) = GIT.run(&mut cmd).await {
+            // If we're creating a shallow clone then the given ref must be
+            // a named ref (branch or tag) rather than a commit directly. We
+            // can't accurately recognize the resulting error here without
+            // making assumptions about git's human-readable output, but we
+            // can at least try a heuristic.
+            if depth > 0 && is_commit_id(git_ref) {
+                return Err(Error::new(
+                    e.kind(),
+                    Some(format!(
+                        "{} (note that setting 'depth' requires 'ref' to be a branch or tag name)",
+                        e
+                    )),
+                ));
+            }
+            return Err(e);
+        }
+
+        // If we didn't add --depth and --branch above then we will now be
+        // on the remote repository's default branch, rather than the
+        // selected ref, so we'll need to fix that before we return.
+        if depth == 0 && !git_ref.is_empty() {
+            self.checkout(dst, git_ref).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        ctx: &Context,
+        dst: &Path,
+        creds: &Credentials<'_>,
+        git_ref: &str,
+        depth: usize,
+    ) -> Result<()> {
+        let mut cmd = git_command(ctx, creds);
+        cmd.arg("fetch").arg("--tags");
+        if depth > 0 {
+            cmd.arg("--depth").arg(depth.to_string());
+        }
+        cmd.arg("origin").current_dir(dst);
+        GIT.run(&mut cmd).await?;
+
+        let git_ref = if git_ref.is_empty() {
+            find_default_branch(dst).await
+        } else {
+            git_ref.to_string()
+        };
+
+        self.checkout(dst, &git_ref).await?;
+
+        // If we're on a branch, then bring it up to date with what we just
+        // fetched. Tags and commits are already exactly what was asked for.
+        let mut cmd = Command::new("git");
+        cmd.args(["show-ref", "-q", "--verify"])
+            .arg(format!("refs/remotes/origin/{}", git_ref))
+            .current_dir(dst);
+        if GIT.run(&mut cmd).await.is_ok() {
+            let mut cmd = Command::new("git");
+            cmd.args(["merge", "--ff-only"])
+                .arg(format!("origin/{}", git_ref))
+                .current_dir(dst);
+            GIT.run(&mut cmd).await?;
+        }
+
+        Ok(())
+    }
+
+    // checkout checks out git_ref in dst. The trailing "--" makes sure that
+    // git_ref is taken for a ref, even if there is a file of the same name.
+    async fn checkout(&self, dst: &Path, git_ref: &str) -> Result<()> {
+        check_ref(git_ref)?;
+
+        let mut cmd = Command::new("git");
+        cmd.arg("checkout").arg(git_ref).arg("--").current_dir(dst);
+        GIT.run(&mut cmd).await
+    }
+
+    // fetch_submodules downloads any configured submodules recursively.
+    async fn fetch_submodules(
+        &self,
+        ctx: &Context,
+        dst: &Path,
+        creds: &Credentials<'_>,
+        depth: usize,
+    ) -> Result<()> {
+        let mut cmd = git_command(ctx, creds);
+        cmd.args(["submodule", "update", "--init", "--recursive"]);
+        if depth > 0 {
+            cmd.arg("--depth").arg(depth.to_string());
+        }
+        cmd.current_dir(dst);
+        GIT.run(&mut cmd).await
+    }
+
+    // get_repo clones or updates the repository at url in dst.
+    async fn get_repo(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        // Extract some query parameters we use
+        let mut git_ref = String::new();
+        let mut ssh_key = String::new();
+        let mut depth = 0;
+        let mut query = Vec::new();
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "ref" => git_ref = value.into_owned(),
+                "sshkey" => ssh_key = value.into_owned(),
+                "depth" => {
+                    depth = value.parse().map_err(|_| {
+                        error::invalid_source(format!("invalid depth '{}' for git url", value))
+                    })?
+                }
+                _ => query.push((key.into_owned(), value.into_owned())),
+            }
+        }
+        check_ref(&git_ref)?;
+
+        let mut url = url.clone();
+        if query.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(query);
+        }
+
+        // If we have an SSH key, then write it to a temporary file that is
+        // removed again once we're done.
+        let ssh_key_file = if ssh_key.is_empty() {
+            None
+        } else {
+            Some(write_ssh_key(&ssh_key)?)
+        };
+        let creds = Credentials {
+            ssh_key_file: ssh_key_file.as_ref().map(|f| f.path()),
+            http: http_credentials(ctx, &url)?,
+        };
+
+        // git doesn't tell us how much there is to download, so we can only
+        // report when the repository starts and finishes.
+        let progress = FileProgress::start(ctx.progress.as_deref(), url.as_str(), None);
+
+        // Clone or update the repository
+        if dst.join(".git").exists() {
+            self.update(ctx, dst, &creds, &git_ref, depth).await?;
+        } else {
+            self.clone(ctx, dst, &creds, &url, &git_ref, depth).await?;
+        }
+
+        // Lastly, download any/all submodules.
+        self.fetch_submodules(ctx, dst, &creds, depth).await?;
+
+        progress.finish();
+
+        Ok(())
+    }
+
+    // get_single_file downloads the repository that contains the file at url
+    // and copies the file out of it.
+    async fn get_single_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let td = tempfile::tempdir().map_err(error::io)?;
+        let repo = td.path().join("repo");
+
+        // Get the filename, and strip the filename from the URL so we can
+        // just get the repository directly.
+        let mut url = url.clone();
+        let path = url.path().to_string();
+        let (dir, filename) = path
+            .rsplit_once('/')
+            .filter(|(_, filename)| !filename.is_empty())
+            .ok_or_else(|| error::invalid_source("no file in git url"))?;
+        url.set_path(dir);
+
+        // Get the full repository
+        self.get(ctx, &repo, &url).await?;
+
+        // Copy the single file
+        let src = repo.join(filename);
+        if !src.is_file() {
+            return Err(error::not_found(format!(
+                "file '{}' not found in repository",
+                filename
+            )));
+        }
+        checksum::copy(ctx.checksum.as_deref(), &src, dst).await?;
+
+        Ok(())
+    }
+}
 
 #[async_trait]
 impl Getter for GitGetter {
-    async fn get(&self, _url: &str) -> Result<&dyn Stream<Item = &[u8]>> {
-        todo!()
+    async fn get(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.get_repo(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
     }
 
-    async fn get_file(&self, _url: &str) -> Result<&dyn Stream<Item = &[u8]>> {
-        todo!()
+    async fn get_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.get_single_file(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
     }
 
-    fn client_mode(&self, _url: &str) -> Result<ClientMode> {
-        todo!()
+    fn client_mode(&self, _url: &Url) -> Result<ClientMode> {
+        Ok(ClientMode::Dir)
+    }
+}
+
+// git_command returns a git command with its environment set up. This is
+// used to pass configuration data to git and ssh, including the timeouts of
+// ctx.
+fn git_command(ctx: &Context, creds: &Credentials<'_>) -> Command {
+    let mut cmd = Command::new("git");
+
+    // git doesn't have an idle timeout, but it can give up on HTTP
+    // transfers that have been slower than a byte per second for as long.
+    if let Some(read) = ctx.timeouts.read {
+        cmd.env("GIT_HTTP_LOW_SPEED_LIMIT", "1")
+            .env("GIT_HTTP_LOW_SPEED_TIME", read.as_secs().max(1).to_string());
+    }
+
+    if let Some(ssh_key_file) = creds.ssh_key_file {
+        // If we have an existing GIT_SSH_COMMAND, we need to append our
+        // options to it.
+        let ssh_command = env::var("GIT_SSH_COMMAND")
+            .ok()
+            .filter(|c| !c.is_empty())
+            .unwrap_or_else(|| "ssh".to_string());
+
+        // The connect timeout is only passed on to ssh here, since setting
+        // GIT_SSH_COMMAND otherwise would override core.sshCommand.
+        let connect_timeout = ctx
+            .timeouts
+            .connect
+            .map(|connect| format!(" -o ConnectTimeout={}", connect.as_secs().max(1)))
+            .unwrap_or_default();
+
+        // GIT_SSH_COMMAND is run through the shell, so quote the path.
+        // Windows paths are passed with forward slashes.
+        let path = ssh_key_file.to_string_lossy().replace('\\', "/");
+        cmd.env(
+            "GIT_SSH_COMMAND",
+            format!(
+                "{} -i '{}' -o IdentitiesOnly=yes{}",
+                ssh_command,
+                path.replace('\'', r"'\''"),
+                connect_timeout
+            ),
+        );
+    }
+
+    if let Some((prefix, authorization)) = &creds.http {
+        // Configuration passed through the environment is appended to any
+        // that the caller already passes this way.
+        let count = env::var("GIT_CONFIG_COUNT")
+            .ok()
+            .and_then(|c| c.parse::<usize>().ok())
+            .unwrap_or(0);
+        cmd.env("GIT_CONFIG_COUNT", (count + 1).to_string())
+            .env(
+                format!("GIT_CONFIG_KEY_{}", count),
+                format!("http.{}.extraHeader", prefix),
+            )
+            .env(
+                format!("GIT_CONFIG_VALUE_{}", count),
+                format!("Authorization: {}", authorization),
+            );
+    }
+
+    cmd
+}
+
+// Credentials are what git authenticates to the remote repository with.
+#[derive(Debug, Default)]
+struct Credentials<'a> {
+    // ssh_key_file is the private key used for SSH sources.
+    ssh_key_file: Option<&'a Path>,
+    // http is the URL prefix of the remote host and the Authorization
+    // header to send to it, for HTTP sources.
+    http: Option<(String, String)>,
+}
+
+// http_credentials looks up the credentials for an HTTP(S) url in the
+// .netrc file. The header is scoped to the remote host so that it isn't
+// sent to others, e.g. when fetching submodules.
+fn http_credentials(ctx: &Context, url: &Url) -> Result<Option<(String, String)>> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Ok(None);
+    }
+    let authorization = match crate::netrc::authorization(ctx.netrc.as_deref(), url)? {
+        Some(authorization) => authorization,
+        None => return Ok(None),
+    };
+
+    let mut prefix = url.clone();
+    prefix.set_path("/");
+    prefix.set_query(None);
+    prefix.set_fragment(None);
+    Ok(Some((prefix.to_string(), authorization)))
+}
+
+// write_ssh_key decodes the base64 encoded key from the sshkey parameter and
+// writes it to a temporary file that only the current user can read.
+fn write_ssh_key(ssh_key: &str) -> Result<NamedTempFile> {
+    // A "+" in the query string decodes to a space, so undo that in case
+    // the key wasn't escaped.
+    let raw = base64::decode(ssh_key.replace(' ', "+"))
+        .map_err(|e| error::invalid_source(format!("error decoding sshkey: {}", e)))?;
+
+    let mut file = tempfile::Builder::new()
+        .prefix("getter-sshkey-")
+        .tempfile()
+        .map_err(error::io)?;
+
+    // Set the permissions prior to writing the key material.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.as_file()
+            .set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(error::io)?;
+    }
+
+    file.write_all(&raw).map_err(error::io)?;
+    file.flush().map_err(error::io)?;
+
+    Ok(file)
+}
+
+// GIT is the git binary.
+const GIT: Tool = Tool {
+    name: "git",
+    authentication: &[
+        "Authentication failed",
+        "Permission denied",
+        "Host key verification failed",
+        "could not read Username",
+    ],
+    not_found: &[
+        "not found",
+        "does not exist",
+        "did not match any file(s) known to git",
+        "couldn't find remote ref",
+        "invalid reference",
+    ],
+    timeout: &["Operation too slow", "timed out"],
+};
+
+// check_ref returns an error for refs that git would take for an option.
+fn check_ref(git_ref: &str) -> Result<()> {
+    if git_ref.starts_with('-') {
+        return Err(error::invalid_source(format!(
+            "invalid ref '{}' for git url",
+            git_ref
+        )));
+    }
+    Ok(())
+}
+
+// is_commit_id returns whether the given ref seems "likely to be" a git
+// commit ID, rather than a named ref.
+fn is_commit_id(git_ref: &str) -> bool {
+    lazy_static::lazy_static! {
+        static ref COMMIT_ID_REGEXP: Regex = Regex::new(r"^[0-9a-fA-F]{7,40}$").unwrap();
+    }
+
+    COMMIT_ID_REGEXP.is_match(git_ref)
+}
+
+// find_default_branch checks the repo's origin remote for its default branch
+// (generally "master"). "master" is returned if an origin default branch
+// can't be determined.
+async fn find_default_branch(dst: &Path) -> String {
+    lazy_static::lazy_static! {
+        static ref DEFAULT_BRANCH_REGEXP: Regex = Regex::new(r"\s->\sorigin/(.*)").unwrap();
+    }
+
+    let output = Command::new("git")
+        .args(["branch", "-r", "--points-at", "refs/remotes/origin/HEAD"])
+        .current_dir(dst)
+        .kill_on_drop(true)
+        .output()
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            DEFAULT_BRANCH_REGEXP
+                .captures(&stdout)
+                .and_then(|m| m.get(1))
+                .map_or_else(|| "master".to_string(), |m| m.as_str().trim().to_string())
+        }
+        _ => "master".to_string(),
+    }
+}
+
+// find_remote_default_branch checks the remote repo's HEAD symref to return
+// the remote repository's default branch. "master" is returned if no HEAD
+// symref exists.
+async fn find_remote_default_branch(ctx: &Context, creds: &Credentials<'_>, url: &Url) -> String {
+    lazy_static::lazy_static! {
+        static ref LS_REMOTE_SYM_REF_REGEXP: Regex =
+            Regex::new(r"ref: refs/heads/([^\s]+).*").unwrap();
+    }
+
+    let output = git_command(ctx, creds)
+        .args(["ls-remote", "--symref", url.as_str(), "HEAD"])
+        .kill_on_drop(true)
+        .output()
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            LS_REMOTE_SYM_REF_REGEXP
+                .captures(&stdout)
+                .and_then(|m| m.get(1))
+                .map_or_else(|| "master".to_string(), |m| m.as_str().to_string())
+        }
+        _ => "master".to_string(),
     }
 }
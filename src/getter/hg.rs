@@ -0,0 +1,150 @@
+use super::command::Tool;
+use super::{Context, Getter};
+use crate::progress::FileProgress;
+use crate::{checksum, error, ClientMode, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::process::Command;
+use url::Url;
+
+/// HgGetter is a Getter implementation that will download a module from
+/// a Mercurial repository.
+///
+/// The system `hg` binary is used, so it must be available on the PATH.
+/// The `ref` query parameter selects the revision, branch or tag to update
+/// the working directory to. The tip of the default branch is used if it
+/// isn't set.
+#[derive(Copy, Clone, Debug)]
+pub struct HgGetter;
+
+impl HgGetter {
+    // get_repo clones or pulls the repository at url into dst and updates
+    // its working directory to the selected revision.
+    async fn get_repo(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let mut rev = String::new();
+        let mut query = Vec::new();
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "ref" => rev = value.into_owned(),
+                _ => query.push((key.into_owned(), value.into_owned())),
+            }
+        }
+        check_rev(&rev)?;
+
+        let mut url = url.clone();
+        if query.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(query);
+        }
+
+        // hg doesn't tell us how much there is to download, so we can only
+        // report when the repository starts and finishes.
+        let progress = FileProgress::start(ctx.progress.as_deref(), url.as_str(), None);
+
+        // Clone the repository without a working directory if we don't have
+        // it yet. Either way we pull afterwards, which is a no-op right
+        // after cloning but brings an existing clone up to date.
+        if !dst.join(".hg").exists() {
+            let mut cmd = hg_command();
+            cmd.args(["clone", "-U", "--"]).arg(url.as_str()).arg(dst);
+            HG.run(&mut cmd).await?;
+        }
+
+        let mut cmd = hg_command();
+        cmd.arg("pull").arg("--").arg(url.as_str()).current_dir(dst);
+        HG.run(&mut cmd).await?;
+
+        let mut cmd = hg_command();
+        cmd.arg("update");
+        if !rev.is_empty() {
+            cmd.arg(format!("--rev={}", rev));
+        }
+        cmd.current_dir(dst);
+        HG.run(&mut cmd).await?;
+
+        progress.finish();
+
+        Ok(())
+    }
+
+    // get_single_file downloads the repository that contains the file at url
+    // and copies the file out of it.
+    async fn get_single_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let td = tempfile::tempdir().map_err(error::io)?;
+        let repo = td.path().join("repo");
+
+        // Get the filename, and strip the filename from the URL so we can
+        // just get the repository directly.
+        let mut url = url.clone();
+        let path = url.path().to_string();
+        let (dir, filename) = path
+            .rsplit_once('/')
+            .filter(|(_, filename)| !filename.is_empty())
+            .ok_or_else(|| error::invalid_source("no file in hg url"))?;
+        url.set_path(dir);
+
+        // Get the full repository
+        self.get_repo(ctx, &repo, &url).await?;
+
+        // Copy the single file
+        let src = repo.join(filename);
+        if !src.is_file() {
+            return Err(error::not_found(format!(
+                "file '{}' not found in repository",
+                filename
+            )));
+        }
+        checksum::copy(ctx.checksum.as_deref(), &src, dst).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Getter for HgGetter {
+    async fn get(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.get_repo(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
+    }
+
+    async fn get_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.get_single_file(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
+    }
+
+    fn client_mode(&self, _url: &Url) -> Result<ClientMode> {
+        Ok(ClientMode::Dir)
+    }
+}
+
+// hg_command returns an hg command whose output doesn't depend on the
+// user's configuration, so that its errors can be recognized. Strict flags
+// keep hg from taking options such as --config out of its arguments.
+fn hg_command() -> Command {
+    let mut cmd = Command::new("hg");
+    cmd.env("HGPLAIN", "+strictflags");
+    cmd
+}
+
+// check_rev returns an error for revisions that hg would take for an
+// option.
+fn check_rev(rev: &str) -> Result<()> {
+    if rev.starts_with('-') {
+        return Err(error::invalid_source(format!(
+            "invalid ref '{}' for hg url",
+            rev
+        )));
+    }
+    Ok(())
+}
+
+// HG is the hg binary.
+const HG: Tool = Tool {
+    name: "hg",
+    authentication: &["authorization failed", "Permission denied"],
+    not_found: &["not found", "unknown revision", "does not exist"],
+    timeout: &[],
+};
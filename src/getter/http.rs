@@ -1,56 +1,773 @@
-use super::Getter;
+use super::{ByteStream, Context, Getter};
 use crate::ClientMode;
 use crate::{error, Result};
 use async_trait::async_trait;
-use core::str::FromStr;
-use futures_util::Stream;
+use std::path::Path;
+use url::Url;
 
-#[derive(Copy, Clone, Debug)]
-pub(crate) struct HttpGetter;
+/// HttpGetter is a Getter implementation that will download from an HTTP
+/// endpoint.
+///
+/// Both HTTP and HTTPS are supported. HTTPS connections are verified
+/// against the system's root certificates, plus any configured in `tls`.
+///
+/// Credentials in the URL are sent using basic authentication. Otherwise,
+/// those for the host in the .netrc file are sent if there are any, see
+/// [Context::netrc].
+///
+/// Redirects are followed. Credentials from the URL aren't sent along when
+/// they lead to another host, which gets its own headers and .netrc
+/// credentials instead.
+///
+/// The ETag, Last-Modified time and size of downloaded files are stored in
+/// a hidden metadata file next to them, along with the URL they were
+/// downloaded from. A file that is downloaded again from the same URL to
+/// the same destination is only requested if it changed on the server,
+/// using `If-None-Match` and `If-Modified-Since`, and is left alone if the
+/// server answers that it didn't. Archives that a [Client](crate::Client)
+/// unpacks don't benefit from this: they are removed once they have been
+/// unpacked, so they are downloaded again every time.
+///
+/// Files are received into a partial file next to their destination. When a
+/// download is interrupted, the next download from the same URL to the same
+/// destination, e.g. a retry, resumes it with a `Range` request, provided the server supports
+/// that and the file hasn't changed according to its ETag or Last-Modified
+/// time.
+///
+/// Directories can't be downloaded over plain HTTP, but a server may
+/// redirect a directory download to another source, like go-getter does:
+/// the URL is requested with a `terraform-get=1` query parameter, and the
+/// response names the real source in an `X-Terraform-Get` header or in a
+/// `<meta name="terraform-get" content="...">` HTML tag. That source is then
+/// downloaded with the detectors and getters of the client. Sources starting
+/// with `/`, `./` or `../` are relative to the URL that redirected to them.
+#[derive(Clone, Debug, Default)]
+pub struct HttpGetter {
+    /// Tls configures how HTTPS connections are verified.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub tls: crate::TlsConfig,
 
-impl HttpGetter {}
+    /// Headers are extra headers sent to the hosts they match. They take
+    /// precedence over credentials from the URL or the .netrc file, and
+    /// those over the headers configured through the environment (see
+    /// [HostHeaders::from_env](crate::HostHeaders::from_env)), which are
+    /// only sent over HTTPS.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub headers: Vec<crate::HostHeaders>,
 
-#[async_trait]
-impl Getter for HttpGetter {
+    /// SourceHeader is the name of the header that redirects directory
+    /// downloads to another source, `X-Terraform-Get` if unset. The name of
+    /// the query parameter and meta tag is the header name in lower case,
+    /// without any `X-` prefix.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub source_header: Option<String>,
+}
+
+// MAX_REDIRECTS is how many HTTP redirects are followed for a single request.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_REDIRECTS: usize = 10;
+
+// MAX_SOURCES is the number of times directory downloads may be redirected
+// to other sources before they are given up.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_SOURCES: usize = 10;
+
+impl HttpGetter {
+    // fetch performs a GET request for the given URL and returns the
+    // response if it was successful.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn fetch(&self, ctx: &Context, url: &Url) -> Result<hyper::Response<hyper::Body>> {
+        let resp = self.send(ctx, url, hyper::HeaderMap::new()).await?;
+        check_status(&resp).map_err(|e| e.with_url(url))?;
+        Ok(resp)
+    }
+
+    // send performs a GET request for the given URL with the given extra
+    // headers, and returns the response whatever its status is. Redirects
+    // are followed, up to MAX_REDIRECTS of them.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn send(
+        &self,
+        ctx: &Context,
+        url: &Url,
+        extra: hyper::HeaderMap,
+    ) -> Result<hyper::Response<hyper::Body>> {
+        use hyper::header::LOCATION;
+
+        let mut target = url.clone();
+        let mut resp = self.send_once(ctx, url, &target, extra.clone()).await?;
+        for _ in 0..MAX_REDIRECTS {
+            if !is_redirect(resp.status()) {
+                break;
+            }
+            let location = resp
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| error::other("redirect without a location").with_url(url))?;
+            target = target
+                .join(location)
+                .map_err(|e| error::invalid_source(e).with_url(url))?;
+
+            resp = self.send_once(ctx, url, &target, extra.clone()).await?;
+        }
+
+        Ok(resp)
+    }
+
+    // send_once performs a single GET request for target, which url was
+    // redirected to if they differ, with the given extra headers.
     #[cfg(not(target_arch = "wasm32"))]
-    async fn get(&self, url: &str) -> Result<&dyn Stream<Item = &[u8]>> {
-        use hyper::{Client, Uri};
+    async fn send_once(
+        &self,
+        ctx: &Context,
+        url: &Url,
+        target: &Url,
+        extra: hyper::HeaderMap,
+    ) -> Result<hyper::Response<hyper::Body>> {
+        use percent_encoding::percent_decode_str;
+
+        // Credentials in the URL take precedence over the .netrc file. They
+        // are sent as a header, not as part of the request URI, and only to
+        // the host of the URL: a redirect to another host gets the headers
+        // and credentials of that host instead.
+        let mut uri = target.clone();
+        let _ = uri.set_username("");
+        let _ = uri.set_password(None);
+        let same_host = url.host_str() == target.host_str()
+            && url.port_or_known_default() == target.port_or_known_default();
+        let authorization = if same_host && (!url.username().is_empty() || url.password().is_some())
+        {
+            let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+            Some(crate::netrc::basic(
+                &decode(url.username()),
+                &decode(url.password().unwrap_or_default()),
+            ))
+        } else {
+            crate::netrc::authorization(ctx.netrc.as_deref(), &uri).map_err(|e| e.with_url(url))?
+        };
+
+        let mut headers = crate::headers::headers(&self.headers, authorization, &uri)
+            .map_err(|e| e.with_url(url))?;
+        headers.extend(extra);
+
+        let mut req = hyper::Request::get(uri.as_str())
+            .body(hyper::Body::empty())
+            .map_err(|e| error::invalid_source(e).with_url(url))?;
+        *req.headers_mut() = headers;
+        request(&self.tls, ctx, req)
+            .await
+            .map_err(|e| e.with_url(url))
+    }
+
+    // download streams the file at url into dst.
+    //
+    // The file is received into a partial file next to dst, which is renamed
+    // into place once it is complete. If the download is interrupted, the
+    // partial file is kept along with the ETag or Last-Modified time of the
+    // remote file, so that the next download of it to dst only requests the
+    // rest, as long as the remote file hasn't changed in the meantime.
+    // Metadata written for another URL is ignored.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn download(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        use crate::progress::FileProgress;
+        use hyper::header::{HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, RANGE};
+        use hyper::StatusCode;
+
+        let parent = parent_dir(dst);
+        let name = dst
+            .file_name()
+            .ok_or_else(|| error::invalid_source("destination has no file name"))?
+            .to_string_lossy();
+        let part = parent.join(format!(".{}.part", name));
+        let part_metadata = parent.join(format!(".{}.part.json", name));
+        let dst_metadata = parent.join(format!(".{}.getter.json", name));
+
+        // Create all the parent directories if needed
+        tokio::fs::create_dir_all(parent).await.map_err(error::io)?;
+
+        // If dst is still the file that was downloaded from url before, it
+        // only needs to be downloaded again if the remote file has changed
+        // since.
+        let source = metadata_url(url);
+        let mut conditional = hyper::HeaderMap::new();
+        let len = std::fs::metadata(dst).ok().map(|m| m.len());
+        if let Some(metadata) = Metadata::read(&dst_metadata, &source)
+            .filter(|m| len.is_some() && (m.size.is_none() || m.size == len))
+        {
+            if let Some(etag) = metadata.etag {
+                conditional.insert(
+                    IF_NONE_MATCH,
+                    HeaderValue::from_str(&etag).map_err(error::other)?,
+                );
+            }
+            if let Some(last_modified) = metadata.last_modified {
+                conditional.insert(
+                    IF_MODIFIED_SINCE,
+                    HeaderValue::from_str(&last_modified).map_err(error::other)?,
+                );
+            }
+        }
+
+        let mut offset = 0;
+        let mut headers = conditional.clone();
+        if let (Ok(len), Some(metadata)) = (
+            std::fs::metadata(&part).map(|m| m.len()),
+            Metadata::read(&part_metadata, &source),
+        ) {
+            if let Some(validator) = metadata.validator().filter(|_| len > 0) {
+                offset = len;
+                let range = format!("bytes={}-", offset);
+                headers.insert(RANGE, HeaderValue::from_str(&range).map_err(error::other)?);
+                headers.insert(
+                    IF_RANGE,
+                    HeaderValue::from_str(validator).map_err(error::other)?,
+                );
+            }
+        }
+
+        let mut resp = self.send(ctx, url, headers).await?;
+        if offset > 0 && resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial file doesn't fit the remote file, so start over.
+            log::debug!("{} can't be resumed, downloading it again", url);
+            offset = 0;
+            resp = self.send(ctx, url, conditional.clone()).await?;
+        }
+        if !conditional.is_empty() && resp.status() == StatusCode::NOT_MODIFIED {
+            log::debug!("{} is unchanged", url);
+            remove_partial(&part, &part_metadata);
+            return Ok(());
+        }
+        check_status(&resp).map_err(|e| e.with_url(url))?;
+
+        // A server that ignores the range sends the whole file instead.
+        let metadata = if resp.status() == StatusCode::PARTIAL_CONTENT {
+            match content_range(resp.headers()) {
+                Some((start, total)) if start == offset => Metadata {
+                    size: total,
+                    ..Metadata::read(&part_metadata, &source).unwrap_or_default()
+                },
+                _ => {
+                    remove_partial(&part, &part_metadata);
+                    return Err(error::network("unexpected content range").with_url(url));
+                }
+            }
+        } else {
+            if offset > 0 {
+                log::debug!("{} changed since it was partially downloaded", url);
+            }
+            offset = 0;
+            let metadata = Metadata::from_headers(&source, resp.headers());
+            // Without a validator, a partial download can't be resumed, so
+            // there is no need to keep it either.
+            if metadata.validator().is_some() {
+                metadata.write(&part_metadata)?;
+            } else {
+                let _ = std::fs::remove_file(&part_metadata);
+            }
+            metadata
+        };
 
-        let uri = Uri::from_str(url).map_err(error::getter)?;
-        let client = Client::new();
-        let resp = client.get(uri).await.map_err(error::getter)?;
+        // The partial file is removed again if the download fails or is
+        // cancelled, unless it can be resumed.
+        let mut guard = PartialGuard {
+            part: &part,
+            metadata: &part_metadata,
+            keep: metadata.validator().is_some(),
+        };
 
-        if resp.status().as_u16() < 200 || resp.status().as_u16() >= 300 {
-            return Err(error::getter(format!(
-                "bad response code: {}",
-                resp.status()
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&part)
+            .await
+            .map_err(error::io)?;
+
+        // The part that was received before is only hashed when resuming,
+        // the rest as it comes in.
+        let mut hasher = ctx.checksum.as_ref().map(|checksum| checksum.hasher());
+        if let Some(hasher) = hasher.as_mut().filter(|_| offset > 0) {
+            hasher.read(&part).await?;
+        }
+
+        let mut progress =
+            FileProgress::start(ctx.progress.as_deref(), url.as_str(), metadata.size);
+        if offset > 0 {
+            progress.add(offset);
+        }
+
+        receive(
+            ctx,
+            resp.into_body(),
+            &mut file,
+            &mut progress,
+            hasher.as_mut(),
+        )
+        .await?;
+        file.sync_all().await.map_err(error::io)?;
+        drop(file);
+
+        let len = std::fs::metadata(&part).map_err(error::io)?.len();
+        if let Some(size) = metadata.size.filter(|size| *size != len) {
+            // The partial file is of no use if it has grown beyond the size
+            // of the remote file.
+            guard.keep = len < size;
+            return Err(error::network(format!(
+                "received {} of {} bytes",
+                len, size
             )));
         }
 
-        // Ok(resp.body())
-        todo!()
+        // A complete file that doesn't match can't be resumed either.
+        if let Some(hasher) = hasher {
+            if let Err(e) = hasher.finish(url.as_str()) {
+                guard.keep = false;
+                return Err(e);
+            }
+        }
+
+        // The metadata of the file that is replaced mustn't outlive it.
+        let _ = std::fs::remove_file(&dst_metadata);
+        std::fs::rename(&part, dst).map_err(error::io)?;
+        guard.keep = false;
+
+        // Losing the metadata only means downloading the file again next
+        // time, so that doesn't fail the download.
+        if metadata.etag.is_some() || metadata.last_modified.is_some() {
+            if let Err(e) = metadata.write(&dst_metadata) {
+                log::debug!("failed to write metadata of {}: {}", url, e);
+            }
+        }
+        progress.finish();
+
+        Ok(())
+    }
+
+    // find_source asks the server for the source that the directory at url
+    // redirects to.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn find_source(&self, ctx: &Context, url: &Url) -> Result<String> {
+        let header = self.source_header.as_deref().unwrap_or("X-Terraform-Get");
+        let name = header.to_ascii_lowercase();
+        let name = name.strip_prefix("x-").unwrap_or(&name);
+
+        let mut get_url = url.clone();
+        get_url.query_pairs_mut().append_pair(name, "1");
+        let resp = self.fetch(ctx, &get_url).await?;
+
+        let source = resp
+            .headers()
+            .get(header)
+            .and_then(|source| source.to_str().ok())
+            .filter(|source| !source.is_empty())
+            .map(|source| source.to_string());
+        let source = match source {
+            Some(source) => Some(source),
+            None => parse_meta(&read_body(ctx, resp).await?, name),
+        };
+
+        source.ok_or_else(|| error::other("no source URL was returned"))
+    }
+
+    // get_source downloads the directory at url by downloading the source it
+    // redirects to into dst.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn get_source(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        if ctx.redirects.contains(url) {
+            return Err(error::other("redirect loop"));
+        }
+        if ctx.redirects.len() >= MAX_SOURCES {
+            return Err(error::other(format!(
+                "stopped after {} redirects",
+                MAX_SOURCES
+            )));
+        }
+
+        let source = ctx.run(self.find_source(ctx, url)).await?;
+        let source =
+            if source.starts_with('/') || source.starts_with("./") || source.starts_with("../") {
+                url.join(&source)
+                    .map_err(error::invalid_source)?
+                    .to_string()
+            } else {
+                source
+            };
+        log::debug!("{} redirects to {}", url, source);
+
+        let mut ctx = ctx.clone();
+        ctx.redirects.push(url.clone());
+
+        // The source is downloaded like the client would, including any
+        // detection, forced getter, subdir and archive.
+        let client = match ctx.client {
+            Some(ref client) => client.derive(&source, dst, ClientMode::Dir),
+            None => {
+                let mut client = crate::Client::new(source, dst, ClientMode::Dir);
+                client.progress = ctx.progress.clone();
+                client.timeouts = ctx.timeouts.clone();
+                client.cancel = ctx.cancel.clone();
+                client.netrc = ctx.netrc.clone();
+                client
+            }
+        };
+        client.get_with(&ctx).await
+    }
+}
+
+// parse_meta returns the content of the first meta tag with the given name
+// in the HTML document html, if any.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_meta(html: &str, name: &str) -> Option<String> {
+    use regex::Regex;
+
+    lazy_static::lazy_static! {
+        static ref META_REGEXP: Regex = Regex::new(r"(?is)<meta\s[^>]*>").unwrap();
+        static ref ATTR_REGEXP: Regex = Regex::new(
+            r#"(?is)([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#
+        )
+        .unwrap();
+    }
+
+    META_REGEXP.find_iter(html).find_map(|meta| {
+        let mut meta_name = None;
+        let mut content = None;
+        for attr in ATTR_REGEXP.captures_iter(meta.as_str()) {
+            let value = (2..=4).find_map(|i| attr.get(i)).map_or("", |v| v.as_str());
+            match attr[1].to_ascii_lowercase().as_str() {
+                "name" => meta_name = Some(value),
+                "content" => content = Some(value),
+                _ => {}
+            }
+        }
+
+        match (meta_name, content) {
+            (Some(meta_name), Some(content)) if meta_name.eq_ignore_ascii_case(name) => Some(
+                content
+                    .replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&quot;", "\"")
+                    .replace("&#39;", "'")
+                    .replace("&amp;", "&"),
+            ),
+            _ => None,
+        }
+    })
+}
+
+// request sends req using the given TLS configuration and the timeouts of
+// ctx, and returns the response whatever its status is. The client is
+// shared with the other requests of the download, see Context::clients.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn request(
+    tls: &crate::TlsConfig,
+    ctx: &Context,
+    req: hyper::Request<hyper::Body>,
+) -> Result<hyper::Response<hyper::Body>> {
+    use crate::timeout;
+
+    let client = ctx.clients.get(tls, ctx.timeouts.connect)?;
+
+    timeout::read(ctx.timeouts.read, client.request(req))
+        .await?
+        .map_err(timeout::network)
+}
+
+// read_body reads the whole body of resp, which is expected to be small.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn read_body(ctx: &Context, resp: hyper::Response<hyper::Body>) -> Result<String> {
+    let body = crate::timeout::read(ctx.timeouts.read, hyper::body::to_bytes(resp.into_body()))
+        .await?
+        .map_err(crate::timeout::network)?;
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+// save streams the body of resp into dst, reporting the progress under the
+// given name. The file is checked against the checksum of ctx, if any,
+// before it is moved into place.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn save(
+    ctx: &Context,
+    resp: hyper::Response<hyper::Body>,
+    dst: &Path,
+    name: &str,
+) -> Result<()> {
+    use crate::progress::FileProgress;
+    use hyper::header::CONTENT_LENGTH;
+
+    let parent = parent_dir(dst);
+
+    // Create all the parent directories if needed
+    tokio::fs::create_dir_all(parent).await.map_err(error::io)?;
+
+    let total = resp
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| len.parse().ok());
+
+    // Download into a temporary file next to dst so that it can be renamed
+    // into place atomically once it is complete. The temporary file is
+    // removed when dropped, so a failed download leaves nothing behind.
+    let tmp = tempfile::Builder::new()
+        .prefix(".getter-")
+        .tempfile_in(parent)
+        .map_err(error::io)?;
+    let mut file = tokio::fs::File::from_std(tmp.reopen().map_err(error::io)?);
+
+    let mut progress = FileProgress::start(ctx.progress.as_deref(), name, total);
+    let mut hasher = ctx.checksum.as_ref().map(|checksum| checksum.hasher());
+    receive(
+        ctx,
+        resp.into_body(),
+        &mut file,
+        &mut progress,
+        hasher.as_mut(),
+    )
+    .await?;
+
+    file.sync_all().await.map_err(error::io)?;
+    drop(file);
+
+    if let Some(hasher) = hasher {
+        hasher.finish(name)?;
+    }
+
+    tmp.persist(dst).map_err(error::io)?;
+    progress.finish();
+
+    Ok(())
+}
+
+// receive streams body into file, reporting the progress as it goes and
+// hashing it with hasher if there is one.
+#[cfg(not(target_arch = "wasm32"))]
+async fn receive(
+    ctx: &Context,
+    mut body: hyper::Body,
+    file: &mut tokio::fs::File,
+    progress: &mut crate::progress::FileProgress<'_>,
+    mut hasher: Option<&mut crate::checksum::Hasher<'_>>,
+) -> Result<()> {
+    use crate::timeout;
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    while let Some(chunk) = timeout::read(ctx.timeouts.read, body.next()).await? {
+        let chunk = chunk.map_err(timeout::network)?;
+        file.write_all(&chunk).await.map_err(error::io)?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&chunk);
+        }
+        progress.add(chunk.len() as u64);
+    }
+
+    Ok(())
+}
+
+// parent_dir returns the directory that dst is in.
+#[cfg(not(target_arch = "wasm32"))]
+fn parent_dir(dst: &Path) -> &Path {
+    match dst.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+// is_redirect returns whether status redirects to the URL in the Location
+// header.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_redirect(status: hyper::StatusCode) -> bool {
+    use hyper::StatusCode;
+
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+// check_status returns an error for responses that weren't successful.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_status(resp: &hyper::Response<hyper::Body>) -> Result<()> {
+    if !resp.status().is_success() {
+        return Err(error::status(resp.status().as_u16(), None)
+            .with_retry_after(crate::retry::retry_after(resp.headers())));
+    }
+    Ok(())
+}
+
+// Metadata identifies the version of a remote file that was downloaded.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+struct Metadata {
+    // url_sha256 identifies the URL the file was downloaded from, see
+    // metadata_url.
+    url_sha256: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    size: Option<u64>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Metadata {
+    // from_headers returns the metadata of the file at url in a full
+    // response with the given headers.
+    fn from_headers(url: &str, headers: &hyper::HeaderMap) -> Metadata {
+        use hyper::header::{CONTENT_LENGTH, ETAG, LAST_MODIFIED};
+
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v: &hyper::header::HeaderValue| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        Metadata {
+            url_sha256: Some(url.to_string()),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            size: header(CONTENT_LENGTH).and_then(|len| len.parse().ok()),
+        }
+    }
+
+    // read reads the metadata of the file at url from path. Metadata that
+    // is missing, can't be read or belongs to another URL is treated alike,
+    // since it only saves downloading again.
+    fn read(path: &Path, url: &str) -> Option<Metadata> {
+        let contents = std::fs::read(path).ok()?;
+        serde_json::from_slice::<Metadata>(&contents)
+            .ok()
+            .filter(|m| m.url_sha256.as_deref() == Some(url))
+    }
+
+    // write writes the metadata to path.
+    fn write(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_vec(self).map_err(error::other)?;
+        std::fs::write(path, contents).map_err(error::io)
+    }
+
+    // validator returns the strong validator of the file, if any, which
+    // tells whether a part of it belongs to the current version.
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+// metadata_url returns url as it is stored in the metadata: the hex SHA-256
+// of the URL without credentials. Its query may hold secrets too, such as
+// presigned signatures or tokens, which don't belong in a file on disk, but
+// also tells files apart, so it is hashed rather than dropped.
+#[cfg(not(target_arch = "wasm32"))]
+fn metadata_url(url: &Url) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut url = url.clone();
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    hex::encode(Sha256::digest(url.as_str()))
+}
+
+// content_range returns the first byte and the total size of the file from
+// the Content-Range header of a partial response.
+#[cfg(not(target_arch = "wasm32"))]
+fn content_range(headers: &hyper::HeaderMap) -> Option<(u64, Option<u64>)> {
+    let range = headers
+        .get(hyper::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?;
+    let (range, total) = range.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+// PartialGuard removes a partial download and its metadata when dropped,
+// unless it is to be kept.
+#[cfg(not(target_arch = "wasm32"))]
+struct PartialGuard<'a> {
+    part: &'a Path,
+    metadata: &'a Path,
+    keep: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for PartialGuard<'_> {
+    fn drop(&mut self) {
+        if !self.keep {
+            remove_partial(self.part, self.metadata);
+        }
+    }
+}
+
+// remove_partial removes a partial download and its metadata.
+#[cfg(not(target_arch = "wasm32"))]
+fn remove_partial(part: &Path, metadata: &Path) {
+    let _ = std::fs::remove_file(part);
+    let _ = std::fs::remove_file(metadata);
+}
+
+#[async_trait]
+impl Getter for HttpGetter {
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn get(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        // A plain HTTP endpoint only serves single files, so directories
+        // have to come from the source that the server redirects to.
+        self.get_source(ctx, dst, url)
+            .await
+            .map_err(|e| e.with_url(url))
     }
 
     #[cfg(target_arch = "wasm32")]
-    async fn get(&self, url: &str) -> Result<&dyn Stream<Item = &[u8]>> {
+    async fn get(&self, _ctx: &Context, _dst: &Path, _url: &Url) -> Result<()> {
         todo!()
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    async fn get_file(&self, _url: &str) -> Result<&dyn Stream<Item = &[u8]>> {
-        todo!()
+    async fn get_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.download(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
     }
 
     #[cfg(target_arch = "wasm32")]
-    async fn get_file(&self, url: &str) -> Result<&dyn Stream<Item = &[u8]>> {
+    async fn get_file(&self, _ctx: &Context, _dst: &Path, _url: &Url) -> Result<()> {
         todo!()
     }
 
-    fn client_mode(&self, url: &str) -> Result<ClientMode> {
-        if url.ends_with('/') {
+    fn client_mode(&self, url: &Url) -> Result<ClientMode> {
+        if url.path().ends_with('/') {
             Ok(ClientMode::Dir)
         } else {
             Ok(ClientMode::File)
         }
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn stream(&self, ctx: &Context, url: &Url) -> Result<ByteStream> {
+        use futures_util::{StreamExt, TryStreamExt};
+        use std::io;
+
+        let resp = ctx
+            .run(self.fetch(ctx, url))
+            .await
+            .map_err(|e| e.with_url(url))?;
+        let body = resp.into_body().map_err(io::Error::other);
+
+        // The stream outlives this call, so the read timeout is applied to
+        // every chunk as it is polled.
+        match ctx.timeouts.read {
+            None => Ok(Box::pin(body)),
+            Some(read) => Ok(Box::pin(tokio_stream::StreamExt::timeout(body, read).map(
+                |chunk| chunk.unwrap_or_else(|e| Err(io::Error::new(io::ErrorKind::TimedOut, e))),
+            ))),
+        }
+    }
 }
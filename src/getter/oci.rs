@@ -0,0 +1,640 @@
+use super::http::{read_body, request, save};
+use super::{join_key, Context, Getter};
+use crate::checksum::FileChecksum;
+use crate::decompressor::{Decompressor, TarDecompressor, TarGzipDecompressor};
+use crate::{error, ClientMode, Error, Result};
+use async_trait::async_trait;
+use hyper::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, LOCATION, WWW_AUTHENTICATE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// OCIGetter is a Getter implementation that will download artifacts from
+/// an OCI registry, such as those pushed with ORAS.
+///
+/// URLs have the form `oci://registry/repository:tag` or
+/// `oci://registry/repository@sha256:digest`, and the tag defaults to
+/// `latest`. The forced form `oci::registry/repository:tag` is turned into
+/// such a URL during detection.
+///
+/// In dir mode every layer of the manifest is downloaded into the
+/// destination. Layers are saved under their `org.opencontainers.image.title`
+/// annotation, except for tarballs that ORAS marks with
+/// `io.deis.oras.content.unpack` and those without a title, which are
+/// unpacked into it. In file mode the manifest must have a single layer,
+/// which is saved as the file. The digest of every layer is verified.
+///
+/// Credentials for the registry are read from the Docker `config.json`.
+/// Registries are spoken to over HTTPS, unless the `plain_http` query
+/// parameter is set to `true`.
+#[derive(Clone, Debug, Default)]
+pub struct OCIGetter {
+    /// Tls configures how HTTPS connections are verified.
+    pub tls: crate::TlsConfig,
+
+    /// DockerConfig is the path of the Docker `config.json` to read
+    /// credentials from. By default `config.json` in the directory named by
+    /// the `DOCKER_CONFIG` environment variable is used, or
+    /// `~/.docker/config.json` if that isn't set.
+    pub docker_config: Option<PathBuf>,
+}
+
+// Session is a conversation with a registry about a single repository. It
+// remembers the authorization that the registry asked for.
+struct Session {
+    // api is the base URL of the registry API.
+    api: Url,
+    registry: String,
+    repository: String,
+    reference: String,
+    credentials: Option<DockerAuth>,
+    authorization: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct DockerConfig {
+    auths: BTreeMap<String, DockerAuth>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+struct DockerAuth {
+    auth: String,
+    username: String,
+    password: String,
+    identitytoken: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    media_type: Option<String>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    size: u64,
+    #[serde(default)]
+    annotations: BTreeMap<String, String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Token {
+    token: String,
+    access_token: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RegistryErrors {
+    errors: Vec<RegistryError>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RegistryError {
+    code: String,
+    message: String,
+}
+
+// MANIFEST_TYPES are the manifest media types that are accepted.
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, \
+     application/vnd.docker.distribution.manifest.v2+json, \
+     application/vnd.oci.image.index.v1+json, \
+     application/vnd.docker.distribution.manifest.list.v2+json";
+
+// TITLE is the annotation that holds the filename of a layer.
+const TITLE: &str = "org.opencontainers.image.title";
+
+// UNPACK is the annotation that ORAS sets on layers that hold a directory.
+const UNPACK: &str = "io.deis.oras.content.unpack";
+
+// MAX_REDIRECTS is how many redirects are followed for a single request.
+// Registries commonly redirect blob downloads to a storage service.
+const MAX_REDIRECTS: usize = 10;
+
+impl OCIGetter {
+    // session starts a session with the registry that url points to.
+    fn session(&self, url: &Url) -> Result<Session> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| error::invalid_source("oci url has no registry"))?;
+        let registry = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+
+        let path = url.path().trim_start_matches('/');
+        let (repository, reference) = match path.split_once('@') {
+            Some((repository, digest)) => (repository, digest),
+            None => match path.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+                _ => (path, "latest"),
+            },
+        };
+        if repository.is_empty() || reference.is_empty() {
+            return Err(error::invalid_source(
+                "oci url should be oci://registry/repository:tag",
+            ));
+        }
+
+        let plain_http = url
+            .query_pairs()
+            .any(|(key, value)| key == "plain_http" && (value == "true" || value == "1"));
+        let scheme = if plain_http { "http" } else { "https" };
+
+        // Docker Hub is known by a different name than its API.
+        let api_host = match registry.as_str() {
+            "docker.io" | "index.docker.io" => "registry-1.docker.io",
+            registry => registry,
+        };
+        let api =
+            Url::parse(&format!("{}://{}/v2/", scheme, api_host)).map_err(error::invalid_source)?;
+
+        Ok(Session {
+            api,
+            credentials: self.credentials(&registry)?,
+            registry,
+            repository: repository.to_string(),
+            reference: reference.to_string(),
+            authorization: None,
+        })
+    }
+
+    // credentials returns the credentials for registry in the Docker
+    // configuration, if there are any.
+    fn credentials(&self, registry: &str) -> Result<Option<DockerAuth>> {
+        let path = match self.docker_config {
+            Some(ref path) => path.clone(),
+            None => match env::var_os("DOCKER_CONFIG") {
+                Some(dir) if !dir.is_empty() => Path::new(&dir).join("config.json"),
+                _ => match env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+                    Some(home) => Path::new(&home).join(".docker").join("config.json"),
+                    None => return Ok(None),
+                },
+            },
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+        let config: DockerConfig = serde_json::from_str(&contents).map_err(|e| {
+            error::authentication(format!("invalid docker config {}: {}", path.display(), e))
+        })?;
+
+        let registry = normalize_registry(registry);
+        Ok(config
+            .auths
+            .into_iter()
+            .find(|(key, _)| normalize_registry(key) == registry)
+            .map(|(_, auth)| auth))
+    }
+
+    // get_manifest fetches the manifest the session refers to.
+    async fn get_manifest(&self, ctx: &Context, session: &mut Session) -> Result<Manifest> {
+        let url = session
+            .api
+            .join(&format!(
+                "{}/manifests/{}",
+                session.repository, session.reference
+            ))
+            .map_err(error::invalid_source)?;
+
+        let resp = self.send(ctx, session, &url, Some(MANIFEST_TYPES)).await?;
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = read_body(ctx, resp).await?;
+
+        // A manifest that was asked for by digest must match it.
+        if let Some(expected) = session.reference.strip_prefix("sha256:") {
+            let actual = hex::encode(Sha256::digest(body.as_bytes()));
+            if actual != expected {
+                return Err(error::checksum_mismatch(format!(
+                    "manifest digest did not match: expected sha256:{}, got sha256:{}",
+                    expected, actual
+                )));
+            }
+        }
+
+        let manifest: Manifest = serde_json::from_str(&body)
+            .map_err(|e| error::other(format!("invalid manifest: {}", e)))?;
+
+        let media_type = manifest.media_type.as_deref().or(content_type.as_deref());
+        if !manifest.manifests.is_empty()
+            || matches!(media_type, Some(t) if t.contains("index") || t.contains("manifest.list"))
+        {
+            return Err(error::invalid_source(
+                "image indexes aren't supported, use the digest of a single manifest",
+            ));
+        }
+
+        Ok(manifest)
+    }
+
+    // get_blob downloads the blob of layer into dst and verifies it.
+    async fn get_blob(
+        &self,
+        ctx: &Context,
+        session: &mut Session,
+        layer: &Descriptor,
+        dst: &Path,
+    ) -> Result<()> {
+        let checksum = FileChecksum::parse(&layer.digest)?;
+
+        let url = session
+            .api
+            .join(&format!("{}/blobs/{}", session.repository, layer.digest))
+            .map_err(error::invalid_source)?;
+
+        let resp = self.send(ctx, session, &url, None).await?;
+        let name = format!(
+            "{}/{}@{}",
+            session.registry, session.repository, layer.digest
+        );
+        save(ctx, resp, dst, &name).await?;
+
+        let size = tokio::fs::metadata(dst).await.map_err(error::io)?.len();
+        if size != layer.size {
+            return Err(error::checksum_mismatch(format!(
+                "size of {} did not match: expected {}, got {}",
+                layer.digest, layer.size, size
+            )));
+        }
+        checksum.verify(dst).await
+    }
+
+    // get_layers downloads every layer of the manifest into dst.
+    async fn get_layers(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let mut session = self.session(url)?;
+        let manifest = self.get_manifest(ctx, &mut session).await?;
+
+        tokio::fs::create_dir_all(dst).await.map_err(error::io)?;
+
+        for layer in &manifest.layers {
+            // Download next to the destination, so the blob can be renamed
+            // into place. The temporary directory takes a bad blob with it.
+            let td = tempfile::Builder::new()
+                .prefix(".getter-")
+                .tempdir_in(dst)
+                .map_err(error::io)?;
+            let blob = td.path().join("blob");
+            self.get_blob(ctx, &mut session, layer, &blob).await?;
+
+            // ORAS gives every file the tar media type by default, so only
+            // the layers that it marks as directories are unpacked, along
+            // with layers that have no title to save them under.
+            let title = layer
+                .annotations
+                .get(TITLE)
+                .filter(|_| layer.annotations.get(UNPACK).map(String::as_str) != Some("true"));
+            let title = match title {
+                Some(title) => title,
+                None if layer.media_type.contains("tar") => {
+                    let dst = dst.to_path_buf();
+                    let progress = ctx.progress.clone();
+                    tokio::task::spawn_blocking(move || untar(&dst, &blob, progress.as_deref()))
+                        .await
+                        .map_err(error::other)??;
+                    continue;
+                }
+                None => {
+                    return Err(error::invalid_source(format!(
+                        "layer {} has neither a title nor a tar media type",
+                        layer.digest
+                    )))
+                }
+            };
+            let path = join_key(dst, title)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.map_err(error::io)?;
+            }
+            tokio::fs::rename(&blob, &path).await.map_err(error::io)?;
+        }
+
+        Ok(())
+    }
+
+    // get_single_layer downloads the only layer of the manifest into dst.
+    async fn get_single_layer(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        let mut session = self.session(url)?;
+        let manifest = self.get_manifest(ctx, &mut session).await?;
+
+        let layer = match manifest.layers.as_slice() {
+            [layer] => layer,
+            layers => {
+                return Err(error::invalid_source(format!(
+                    "manifest has {} layers, a single file needs exactly one",
+                    layers.len()
+                )))
+            }
+        };
+
+        let parent = match dst.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        tokio::fs::create_dir_all(parent).await.map_err(error::io)?;
+        let td = tempfile::Builder::new()
+            .prefix(".getter-")
+            .tempdir_in(parent)
+            .map_err(error::io)?;
+        let blob = td.path().join("blob");
+        self.get_blob(ctx, &mut session, layer, &blob).await?;
+
+        tokio::fs::rename(&blob, dst).await.map_err(error::io)
+    }
+
+    // send sends a GET request for url and returns the response if it was
+    // successful. It authorizes the session when the registry asks for it,
+    // and follows redirects.
+    async fn send(
+        &self,
+        ctx: &Context,
+        session: &mut Session,
+        url: &Url,
+        accept: Option<&str>,
+    ) -> Result<Response<Body>> {
+        let mut resp = self
+            .get(ctx, url, accept, session.authorization.as_deref())
+            .await?;
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            let challenge = resp
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+                .ok_or_else(|| error::authentication("registry requires authentication"))?;
+            session.authorization = Some(self.authorize(ctx, session, &challenge).await?);
+
+            resp = self
+                .get(ctx, url, accept, session.authorization.as_deref())
+                .await?;
+        }
+
+        // The authorization is meant for the registry only, so it isn't
+        // sent along when following redirects.
+        let mut url = url.clone();
+        for _ in 0..MAX_REDIRECTS {
+            if !resp.status().is_redirection() {
+                break;
+            }
+            let location = resp
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| error::other("redirect without a location"))?;
+            url = url.join(location).map_err(error::invalid_source)?;
+
+            resp = self.get(ctx, &url, accept, None).await?;
+        }
+
+        if !resp.status().is_success() {
+            return Err(status_error(ctx, resp).await);
+        }
+
+        Ok(resp)
+    }
+
+    // get sends a single GET request for url.
+    async fn get(
+        &self,
+        ctx: &Context,
+        url: &Url,
+        accept: Option<&str>,
+        authorization: Option<&str>,
+    ) -> Result<Response<Body>> {
+        let mut req = Request::get(url.as_str());
+        if let Some(accept) = accept {
+            req = req.header(ACCEPT, accept);
+        }
+        if let Some(authorization) = authorization {
+            req = req.header(AUTHORIZATION, authorization);
+        }
+
+        let req = req.body(Body::empty()).map_err(error::invalid_source)?;
+        request(&self.tls, ctx, req).await
+    }
+
+    // authorize answers the authentication challenge of the registry and
+    // returns the authorization header to send from now on.
+    async fn authorize(&self, ctx: &Context, session: &Session, challenge: &str) -> Result<String> {
+        let (scheme, params) = parse_challenge(challenge);
+        let basic = session.credentials.as_ref().and_then(DockerAuth::basic);
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            return basic.ok_or_else(|| {
+                error::authentication(format!("no credentials for registry {}", session.registry))
+            });
+        }
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return Err(error::authentication(format!(
+                "unsupported authentication scheme '{}'",
+                scheme
+            )));
+        }
+
+        let realm = params
+            .get("realm")
+            .ok_or_else(|| error::authentication("bearer challenge without a realm"))?;
+        let mut url = Url::parse(realm).map_err(error::authentication)?;
+        let scope = params
+            .get("scope")
+            .cloned()
+            .unwrap_or_else(|| format!("repository:{}:pull", session.repository));
+        let service = params.get("service").cloned().unwrap_or_default();
+
+        let identity_token = session
+            .credentials
+            .as_ref()
+            .map(|c| c.identitytoken.as_str())
+            .filter(|t| !t.is_empty());
+        let req = match identity_token {
+            // An identity token is exchanged for an access token through
+            // the OAuth 2.0 endpoint of the realm.
+            Some(identity_token) => {
+                let body = url::form_urlencoded::Serializer::new(String::new())
+                    .append_pair("grant_type", "refresh_token")
+                    .append_pair("refresh_token", identity_token)
+                    .append_pair("service", &service)
+                    .append_pair("scope", &scope)
+                    .append_pair("client_id", "getter")
+                    .finish();
+                Request::post(url.as_str())
+                    .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from(body))
+            }
+            None => {
+                url.query_pairs_mut()
+                    .append_pair("service", &service)
+                    .append_pair("scope", &scope);
+                let mut req = Request::get(url.as_str());
+                if let Some(ref basic) = basic {
+                    req = req.header(AUTHORIZATION, basic);
+                }
+                req.body(Body::empty())
+            }
+        }
+        .map_err(error::invalid_source)?;
+
+        let resp = request(&self.tls, ctx, req).await?;
+        let status = resp.status();
+        let body = read_body(ctx, resp).await?;
+        if !status.is_success() {
+            return Err(error::authentication(format!(
+                "obtaining a token for {} failed ({}): {}",
+                session.registry,
+                status,
+                body.trim()
+            )));
+        }
+
+        let token: Token = serde_json::from_str(&body)
+            .map_err(|e| error::authentication(format!("invalid token: {}", e)))?;
+        let token = if token.token.is_empty() {
+            token.access_token
+        } else {
+            token.token
+        };
+        if token.is_empty() {
+            return Err(error::authentication("registry returned an empty token"));
+        }
+
+        Ok(format!("Bearer {}", token))
+    }
+}
+
+#[async_trait]
+impl Getter for OCIGetter {
+    async fn get(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.get_layers(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
+    }
+
+    async fn get_file(&self, ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+        ctx.run(self.get_single_layer(ctx, dst, url))
+            .await
+            .map_err(|e| e.with_url(url))
+    }
+
+    fn client_mode(&self, _url: &Url) -> Result<ClientMode> {
+        Ok(ClientMode::Dir)
+    }
+}
+
+impl DockerAuth {
+    // basic returns the basic authorization header for these credentials,
+    // if there are a username and password.
+    fn basic(&self) -> Option<String> {
+        let auth = if !self.auth.is_empty() {
+            self.auth.clone()
+        } else if !self.username.is_empty() {
+            base64::encode(format!("{}:{}", self.username, self.password))
+        } else {
+            return None;
+        };
+
+        Some(format!("Basic {}", auth))
+    }
+}
+
+// normalize_registry returns the host of a registry as it appears in the
+// auths of a Docker config, where it may be a URL.
+fn normalize_registry(registry: &str) -> String {
+    let registry = registry
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let registry = registry.split('/').next().unwrap_or(registry);
+
+    match registry {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io".to_string(),
+        registry => registry.to_string(),
+    }
+}
+
+// parse_challenge splits a WWW-Authenticate header into its scheme and
+// parameters, e.g. `Bearer realm="https://auth.example.com/token",
+// service="registry"`.
+fn parse_challenge(challenge: &str) -> (String, BTreeMap<String, String>) {
+    let challenge = challenge.trim();
+    let (scheme, rest) = challenge.split_once(' ').unwrap_or((challenge, ""));
+
+    let mut params = BTreeMap::new();
+    let mut rest = rest.trim_start();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_lowercase();
+        let value = value.trim_start();
+
+        let (value, remainder) = match value.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => match value.find(',') {
+                Some(end) => (&value[..end], &value[end..]),
+                None => (value, ""),
+            },
+        };
+
+        params.insert(key, value.to_string());
+        rest = remainder.trim_start_matches(',').trim_start();
+    }
+
+    (scheme.to_string(), params)
+}
+
+// untar unpacks the tar layer at src into dst, whether it is compressed
+// with gzip or not.
+fn untar(dst: &Path, src: &Path, progress: Option<&dyn crate::ProgressListener>) -> Result<()> {
+    let mut magic = [0; 2];
+    let gzip = fs::File::open(src)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| magic == [0x1f, 0x8b])
+        .unwrap_or(false);
+
+    if gzip {
+        TarGzipDecompressor.decompress(dst, src, true, progress)
+    } else {
+        TarDecompressor.decompress(dst, src, true, progress)
+    }
+}
+
+// status_error turns an unsuccessful response into an error, including the
+// reason the registry gave for it.
+async fn status_error(ctx: &Context, resp: Response<Body>) -> Error {
+    let status = resp.status().as_u16();
+    let retry_after = crate::retry::retry_after(resp.headers());
+
+    let reasons: RegistryErrors = match read_body(ctx, resp).await {
+        Ok(body) => serde_json::from_str(&body).unwrap_or_default(),
+        Err(_) => RegistryErrors::default(),
+    };
+    let message = reasons
+        .errors
+        .iter()
+        .map(|e| format!("{}: {}", e.code, e.message))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = format!("{} ({})", message, status);
+
+    error::status(status, Some(message)).with_retry_after(retry_after)
+}
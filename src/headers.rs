@@ -0,0 +1,157 @@
+use crate::{error, Result};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use std::env;
+use std::fmt;
+use url::Url;
+
+/// HostHeaders are extra HTTP headers that are sent with every request to
+/// the hosts that match a pattern, e.g. to authenticate to them.
+///
+/// The pattern is a host name in which `*` matches any run of characters,
+/// so `*.example.com` matches all subdomains of `example.com`, but not
+/// `example.com` itself. Matching ignores case and the port.
+#[derive(Clone, Default)]
+pub struct HostHeaders {
+    /// Host is the pattern of the hosts to send the headers to.
+    pub host: String,
+
+    /// Headers are the names and values of the headers to send. They
+    /// replace any headers of the same name that would be sent otherwise.
+    pub headers: Vec<(String, String)>,
+}
+
+impl HostHeaders {
+    /// Returns the headers for well-known hosts that are configured through
+    /// the environment:
+    ///
+    /// * `GITHUB_TOKEN` is sent as a bearer token to `github.com` and its
+    ///   subdomains, e.g. `api.github.com`.
+    /// * `GITLAB_TOKEN` is sent as `PRIVATE-TOKEN` to `gitlab.com` and its
+    ///   subdomains. Self-hosted instances need headers of their own, see
+    ///   [HttpGetter::headers](crate::getter::HttpGetter::headers).
+    /// * `BITBUCKET_TOKEN` is sent as a bearer token to `bitbucket.org` and
+    ///   its subdomains, e.g. `api.bitbucket.org`.
+    pub fn from_env() -> Vec<HostHeaders> {
+        HostHeaders::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Returns the headers for well-known hosts like
+    /// [from_env](HostHeaders::from_env) does, but looks the variables up
+    /// with var instead of in the environment.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Vec<HostHeaders> {
+        let mut rules = Vec::new();
+        let mut add = |key: &str, hosts: &[&str], name: &str, prefix: &str| {
+            if let Some(token) = var(key).filter(|t| !t.is_empty()) {
+                for host in hosts {
+                    rules.push(HostHeaders {
+                        host: host.to_string(),
+                        headers: vec![(name.to_string(), format!("{}{}", prefix, token))],
+                    });
+                }
+            }
+        };
+
+        add(
+            "GITHUB_TOKEN",
+            &["github.com", "*.github.com"],
+            "Authorization",
+            "Bearer ",
+        );
+        add(
+            "GITLAB_TOKEN",
+            &["gitlab.com", "*.gitlab.com"],
+            "PRIVATE-TOKEN",
+            "",
+        );
+        add(
+            "BITBUCKET_TOKEN",
+            &["bitbucket.org", "*.bitbucket.org"],
+            "Authorization",
+            "Bearer ",
+        );
+
+        rules
+    }
+
+    /// Returns whether host matches the pattern of these headers.
+    pub fn matches(&self, host: &str) -> bool {
+        glob(
+            self.host.to_ascii_lowercase().as_bytes(),
+            host.to_ascii_lowercase().as_bytes(),
+        )
+    }
+}
+
+// The values are left out, since they are usually secrets.
+impl fmt::Debug for HostHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostHeaders")
+            .field("host", &self.host)
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .iter()
+                    .map(|(name, _)| (name.as_str(), "redacted"))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+// glob returns whether s matches pattern, in which '*' matches any run of
+// characters.
+fn glob(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((b'*', rest)) => (0..=s.len()).any(|i| glob(rest, &s[i..])),
+        Some((c, rest)) => s.first() == Some(c) && glob(rest, &s[1..]),
+    }
+}
+
+// apply adds the headers of the rules that match the host of url to
+// headers, replacing any that are already there. Later rules take
+// precedence over earlier ones.
+fn apply(rules: &[HostHeaders], url: &Url, headers: &mut HeaderMap) -> Result<()> {
+    let host = match url.host_str() {
+        Some(host) => host,
+        None => return Ok(()),
+    };
+
+    for rule in rules.iter().filter(|rule| rule.matches(host)) {
+        for (name, value) in &rule.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(error::other)?;
+            let mut value = HeaderValue::from_str(value).map_err(error::other)?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+    }
+
+    Ok(())
+}
+
+// headers returns the headers to send to url: those configured through the
+// environment for HTTPS URLs, then authorization if any, and finally those
+// of rules.
+pub(crate) fn headers(
+    rules: &[HostHeaders],
+    authorization: Option<String>,
+    url: &Url,
+) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    // Tokens from the environment are never sent in the clear.
+    if url.scheme() == "https" {
+        apply(&HostHeaders::from_env(), url, &mut headers)?;
+    }
+
+    if let Some(authorization) = authorization {
+        let mut value = HeaderValue::from_str(&authorization).map_err(error::other)?;
+        value.set_sensitive(true);
+        headers.insert(hyper::header::AUTHORIZATION, value);
+    }
+
+    apply(rules, url, &mut headers)?;
+
+    Ok(headers)
+}
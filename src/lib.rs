@@ -12,7 +12,7 @@
 //!
 //! Protocols and detectors are extensible.
 //!
-//! To get started, see Client.
+//! To get started, see [Client].
 
 mod error;
 pub use error::*;
@@ -21,9 +21,39 @@ mod client_mode;
 pub use crate::client_mode::ClientMode;
 
 pub mod detector;
-pub use crate::detector::{detect, DETECTORS};
+pub use crate::detector::{detect, Detector, DETECTORS};
 
-mod client;
+pub mod decompressor;
+pub use crate::decompressor::{Decompressor, DECOMPRESSORS};
 
-mod getter;
-pub use crate::getter::GETTERS;
+pub mod getter;
+pub use crate::getter::{Getter, GETTERS};
+
+pub mod progress;
+pub use crate::progress::ProgressListener;
+
+cfg_if::cfg_if! {
+    if #[cfg(not(target_arch = "wasm32"))] {
+        mod checksum;
+
+        mod copy_dir;
+
+        mod headers;
+        pub use crate::headers::HostHeaders;
+
+        mod netrc;
+
+        mod retry;
+        pub use crate::retry::RetryPolicy;
+
+        mod timeout;
+        pub use crate::timeout::Timeouts;
+        pub use tokio_util::sync::CancellationToken;
+
+        mod tls;
+        pub use crate::tls::{ClientIdentity, TlsConfig};
+
+        mod client;
+        pub use crate::client::Client;
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{error, Result};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+// Netrc is the contents of a .netrc file, which holds credentials for the
+// machines a user logs in to.
+#[derive(Debug, Default)]
+pub(crate) struct Netrc {
+    machines: Vec<Machine>,
+}
+
+// Machine is a machine entry of a .netrc file. The default entry, which
+// matches any machine, has no name.
+#[derive(Debug, Default)]
+struct Machine {
+    name: Option<String>,
+    login: String,
+    password: String,
+}
+
+impl Netrc {
+    // load reads the .netrc file at path, or the one named by the NETRC
+    // environment variable, or ~/.netrc, in that order. A missing file is
+    // only an error if its path was given explicitly.
+    pub(crate) fn load(path: Option<&Path>) -> Result<Netrc> {
+        if let Some(path) = path {
+            let contents = fs::read_to_string(path).map_err(error::io)?;
+            return Ok(Netrc::parse(&contents));
+        }
+
+        let path = match env::var_os("NETRC") {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => match env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+                Some(home) if cfg!(windows) => Path::new(&home).join("_netrc"),
+                Some(home) => Path::new(&home).join(".netrc"),
+                None => return Ok(Netrc::default()),
+            },
+        };
+
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Netrc::parse(&contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Netrc::default()),
+            Err(e) => Err(error::io(e)),
+        }
+    }
+
+    // parse parses the contents of a .netrc file. Tokens that aren't
+    // understood are skipped, as are the bodies of macro definitions.
+    pub(crate) fn parse(contents: &str) -> Netrc {
+        let mut netrc = Netrc::default();
+        let mut tokens = Tokens { rest: contents };
+
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "machine" => netrc.machines.push(Machine {
+                    name: tokens.next(),
+                    ..Machine::default()
+                }),
+                "default" => netrc.machines.push(Machine::default()),
+                "login" | "password" | "account" => {
+                    let value = tokens.next().unwrap_or_default();
+                    if let Some(machine) = netrc.machines.last_mut() {
+                        match token.as_str() {
+                            "login" => machine.login = value,
+                            "password" => machine.password = value,
+                            _ => {}
+                        }
+                    }
+                }
+                "macdef" => {
+                    tokens.next();
+                    tokens.skip_macro();
+                }
+                _ => {}
+            }
+        }
+
+        netrc
+    }
+
+    // credentials returns the login and password for host. The first
+    // machine entry for the host is used, and the default entry if there is
+    // none.
+    pub(crate) fn credentials(&self, host: &str) -> Option<(&str, &str)> {
+        let machine = self
+            .machines
+            .iter()
+            .find(|m| matches!(m.name, Some(ref name) if name.eq_ignore_ascii_case(host)))
+            .or_else(|| self.machines.iter().find(|m| m.name.is_none()))?;
+
+        if machine.login.is_empty() && machine.password.is_empty() {
+            return None;
+        }
+        Some((&machine.login, &machine.password))
+    }
+}
+
+// Tokens splits the contents of a .netrc file into its tokens.
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl Tokens<'_> {
+    // skip_macro skips the body of a macro definition, which runs until the
+    // next empty line.
+    fn skip_macro(&mut self) {
+        // The body starts on the line after the macro name.
+        let lines = self.rest.split_inclusive('\n').skip(1);
+        let mut skipped = self.rest.find('\n').map_or(self.rest.len(), |idx| idx + 1);
+        for line in lines {
+            skipped += line.len();
+            if line.trim_end_matches(['\r', '\n']).is_empty() {
+                break;
+            }
+        }
+        self.rest = &self.rest[skipped..];
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            self.rest = self.rest.trim_start();
+            // Lines starting with '#' are comments.
+            if self.rest.starts_with('#') {
+                self.rest = self.rest.find('\n').map_or("", |idx| &self.rest[idx..]);
+                continue;
+            }
+            break;
+        }
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        // Tokens may be quoted to include whitespace, with backslash
+        // escapes inside the quotes.
+        if let Some(quoted) = self.rest.strip_prefix('"') {
+            let mut token = String::new();
+            let mut chars = quoted.char_indices();
+            while let Some((idx, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.rest = &quoted[idx + 1..];
+                        return Some(token);
+                    }
+                    '\\' => {
+                        if let Some((_, c)) = chars.next() {
+                            token.push(match c {
+                                'n' => '\n',
+                                'r' => '\r',
+                                't' => '\t',
+                                c => c,
+                            });
+                        }
+                    }
+                    c => token.push(c),
+                }
+            }
+            self.rest = "";
+            return Some(token);
+        }
+
+        let end = self
+            .rest
+            .find(char::is_whitespace)
+            .unwrap_or(self.rest.len());
+        let token = self.rest[..end].to_string();
+        self.rest = &self.rest[end..];
+        Some(token)
+    }
+}
+
+// authorization returns the basic authorization header for url from the
+// .netrc file, see Netrc::load. Nothing is returned for URLs that carry
+// their own credentials.
+pub(crate) fn authorization(path: Option<&Path>, url: &Url) -> Result<Option<String>> {
+    if !url.username().is_empty() || url.password().is_some() {
+        return Ok(None);
+    }
+    let host = match url.host_str() {
+        Some(host) => host,
+        None => return Ok(None),
+    };
+
+    let netrc = Netrc::load(path)?;
+    Ok(netrc
+        .credentials(host)
+        .map(|(login, password)| basic(login, password)))
+}
+
+// basic returns the basic authorization header for the given credentials.
+pub(crate) fn basic(login: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        base64::encode(format!("{}:{}", login, password))
+    )
+}
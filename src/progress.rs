@@ -0,0 +1,69 @@
+#![cfg_attr(target_arch = "wasm32", allow(unused))]
+//! Progress reporting for downloads and archive extraction.
+
+use std::fmt;
+
+/// ProgressListener is notified about the files that getters download and
+/// decompressors extract, so that callers can show a progress bar.
+///
+/// Downloads are named by their URL and extracted files by their path
+/// inside the archive. All methods do nothing by default.
+pub trait ProgressListener: fmt::Debug + Sync + Send + 'static {
+    /// start is called when a file starts being received. total is its size
+    /// in bytes, if it is known up front (e.g. from Content-Length).
+    fn start(&self, _name: &str, _total: Option<u64>) {}
+
+    /// progress is called whenever bytes of a file have been received, with
+    /// the number of bytes received so far and its total size, if known.
+    fn progress(&self, _name: &str, _received: u64, _total: Option<u64>) {}
+
+    /// finish is called when a file has been received completely.
+    fn finish(&self, _name: &str) {}
+}
+
+// FileProgress reports the progress of a single file to an optional
+// listener.
+#[derive(Debug)]
+pub(crate) struct FileProgress<'a> {
+    listener: Option<&'a dyn ProgressListener>,
+    name: String,
+    received: u64,
+    total: Option<u64>,
+}
+
+impl<'a> FileProgress<'a> {
+    // start reports the start of the named file and returns a FileProgress
+    // to report the rest with.
+    pub(crate) fn start(
+        listener: Option<&'a dyn ProgressListener>,
+        name: impl Into<String>,
+        total: Option<u64>,
+    ) -> FileProgress<'a> {
+        let name = name.into();
+        if let Some(listener) = listener {
+            listener.start(&name, total);
+        }
+
+        FileProgress {
+            listener,
+            name,
+            received: 0,
+            total,
+        }
+    }
+
+    // add reports that another n bytes have been received.
+    pub(crate) fn add(&mut self, n: u64) {
+        self.received += n;
+        if let Some(listener) = self.listener {
+            listener.progress(&self.name, self.received, self.total);
+        }
+    }
+
+    // finish reports that the file has been received completely.
+    pub(crate) fn finish(self) {
+        if let Some(listener) = self.listener {
+            listener.finish(&self.name);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::Result;
+use hyper::header::{HeaderMap, RETRY_AFTER};
+use rand::Rng;
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+
+/// RetryPolicy decides how often and how quickly downloads that failed with
+/// a retryable error (see [Error::is_retryable](crate::Error::is_retryable))
+/// are attempted again.
+///
+/// The delay between attempts grows exponentially with some random jitter,
+/// unless the server asked for a specific delay with `Retry-After`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// MaxAttempts is the maximum number of attempts, including the first
+    /// one. A value of 1 disables retries.
+    pub max_attempts: u32,
+
+    /// InitialBackoff is the delay before the first retry. It doubles with
+    /// every further retry.
+    pub initial_backoff: Duration,
+
+    /// MaxBackoff caps the delay between attempts, including delays that
+    /// are requested by the server.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns a policy that never retries.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // retry calls f until it succeeds, fails with an error that isn't
+    // retryable, or the attempts are used up.
+    pub(crate) async fn retry<T, F, Fut>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Err(e) if e.is_retryable() && attempt < self.max_attempts => {
+                    let delay = self.backoff(attempt, e.retry_after());
+                    log::debug!(
+                        "attempt {} of {} failed, retrying in {:?}: {}",
+                        attempt,
+                        self.max_attempts,
+                        delay,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // backoff returns the delay after the given failed attempt.
+    fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_backoff);
+        }
+
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+
+        // Wait somewhere between half and all of the backoff, so that many
+        // clients failing at once don't all come back at once.
+        let half = backoff / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=backoff - half)
+    }
+}
+
+// retry_after parses the Retry-After header of a response, which is either
+// a number of seconds or an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
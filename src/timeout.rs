@@ -0,0 +1,98 @@
+use crate::{error, Error, Result};
+use std::error::Error as StdError;
+use std::future::Future;
+use std::io;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Timeouts limits how long downloads may take, so that a stalled server
+/// can't make them hang forever. Timeouts that are `None` aren't enforced.
+///
+/// By default connecting may take 30 seconds and the server may go quiet
+/// for 60 seconds, but there is no limit on the total time.
+#[derive(Clone, Debug)]
+pub struct Timeouts {
+    /// Connect limits how long establishing a connection to a server may
+    /// take.
+    pub connect: Option<Duration>,
+
+    /// Read limits how long to wait for a server to send more data, such
+    /// as the response headers or the next chunk of the body.
+    pub read: Option<Duration>,
+
+    /// Total limits how long a whole [Client::get](crate::Client::get) may
+    /// take, including detection, retries and checksum files.
+    pub total: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts {
+            connect: Some(Duration::from_secs(30)),
+            read: Some(Duration::from_secs(60)),
+            total: None,
+        }
+    }
+}
+
+impl Timeouts {
+    /// Returns timeouts that never expire.
+    pub fn none() -> Timeouts {
+        Timeouts {
+            connect: None,
+            read: None,
+            total: None,
+        }
+    }
+}
+
+// guard runs fut until it completes, the deadline passes or cancel is
+// cancelled, whichever happens first. fut is dropped when it doesn't
+// complete, which aborts whatever it was doing.
+pub(crate) async fn guard<T>(
+    deadline: Option<Instant>,
+    cancel: &CancellationToken,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let expired = async {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        biased;
+
+        _ = cancel.cancelled() => Err(error::cancelled()),
+        _ = expired => Err(error::timeout("deadline exceeded")),
+        result = fut => result,
+    }
+}
+
+// read waits for fut, which reads from a server, for at most the given
+// read timeout.
+pub(crate) async fn read<F: Future>(timeout: Option<Duration>, fut: F) -> Result<F::Output> {
+    match timeout {
+        None => Ok(fut.await),
+        Some(timeout) => tokio::time::timeout(timeout, fut)
+            .await
+            .map_err(|_| error::timeout(format!("no data received for {:?}", timeout))),
+    }
+}
+
+// network turns an error of the HTTP client into a timeout if the
+// connection timed out, and a network error otherwise.
+pub(crate) fn network(e: hyper::Error) -> Error {
+    let mut source: Option<&(dyn StdError + 'static)> = Some(&e);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            if err.kind() == io::ErrorKind::TimedOut {
+                return error::timeout(e);
+            }
+        }
+        source = err.source();
+    }
+
+    error::network(e)
+}
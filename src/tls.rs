@@ -0,0 +1,139 @@
+use crate::{error, Result};
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
+use native_tls::{Certificate, Identity, TlsConnector};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// TlsConfig configures how HTTPS connections made by the getters and
+/// detectors are verified.
+///
+/// By default only the system's root certificates are trusted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// RootCertificates is a list of additional root certificates to trust.
+    /// Each entry is either a PEM file, which may hold a bundle of several
+    /// certificates, or a directory of such files.
+    pub root_certificates: Vec<PathBuf>,
+
+    /// ClientIdentity is the certificate presented to servers that require
+    /// client authentication.
+    pub client_identity: Option<ClientIdentity>,
+}
+
+/// ClientIdentity is a client certificate and its private key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// Certificate is the path to the PEM encoded certificate chain.
+    pub certificate: PathBuf,
+
+    /// Key is the path to the PEM encoded PKCS #8 private key.
+    pub key: PathBuf,
+}
+
+impl TlsConfig {
+    // https_connector builds a connector that speaks both HTTP and HTTPS
+    // using this configuration, and gives up connecting after the given
+    // timeout.
+    pub(crate) fn https_connector(
+        &self,
+        connect_timeout: Option<Duration>,
+    ) -> Result<HttpsConnector<HttpConnector>> {
+        let mut builder = TlsConnector::builder();
+
+        for path in &self.root_certificates {
+            for cert in read_certificates(path)? {
+                builder.add_root_certificate(cert);
+            }
+        }
+
+        if let Some(ref identity) = self.client_identity {
+            let cert = fs::read(&identity.certificate).map_err(error::io)?;
+            let key = fs::read(&identity.key).map_err(error::io)?;
+            builder.identity(Identity::from_pkcs8(&cert, &key).map_err(error::other)?);
+        }
+
+        let tls = builder.build().map_err(error::other)?;
+
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_connect_timeout(connect_timeout);
+
+        Ok(HttpsConnector::from((http, tls.into())))
+    }
+}
+
+// HttpClient is an HTTP client that speaks both HTTP and HTTPS.
+pub(crate) type HttpClient = hyper::Client<HttpsConnector<HttpConnector>>;
+
+// Clients are the HTTP clients of a download, one for each TLS
+// configuration and connect timeout, which are built on first use. The
+// requests of the download share them, so that certificates are only read
+// once and connections are reused, e.g. when following redirects.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Clients(Arc<Mutex<Vec<(ClientKey, HttpClient)>>>);
+
+// ClientKey is the TLS configuration and connect timeout of a client.
+type ClientKey = (TlsConfig, Option<Duration>);
+
+impl Clients {
+    // get returns the client for the given TLS configuration and connect
+    // timeout.
+    pub(crate) fn get(
+        &self,
+        tls: &TlsConfig,
+        connect_timeout: Option<Duration>,
+    ) -> Result<HttpClient> {
+        let mut clients = self.0.lock().unwrap();
+        if let Some((_, client)) = clients
+            .iter()
+            .find(|((t, timeout), _)| t == tls && *timeout == connect_timeout)
+        {
+            return Ok(client.clone());
+        }
+
+        let client = hyper::Client::builder().build(tls.https_connector(connect_timeout)?);
+        clients.push(((tls.clone(), connect_timeout), client.clone()));
+        Ok(client)
+    }
+}
+
+// read_certificates reads every certificate in the PEM file at path, or in
+// every file of the directory at path.
+fn read_certificates(path: &Path) -> Result<Vec<Certificate>> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+
+    let mut files = Vec::new();
+    if path.is_dir() {
+        for entry in fs::read_dir(path).map_err(error::io)? {
+            let entry = entry.map_err(error::io)?;
+            if entry.path().is_file() {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    let mut certs = Vec::new();
+    for file in files {
+        let pem = fs::read_to_string(&file).map_err(error::io)?;
+
+        // Certificate::from_pem only reads the first certificate, so split
+        // bundles up ourselves.
+        for (idx, _) in pem.match_indices(BEGIN) {
+            let cert = Certificate::from_pem(&pem.as_bytes()[idx..])
+                .map_err(|e| error::other(format!("invalid certificate in {:?}: {}", file, e)))?;
+            certs.push(cert);
+        }
+    }
+
+    if certs.is_empty() {
+        return Err(error::other(format!("no certificates found in {:?}", path)));
+    }
+
+    Ok(certs)
+}
@@ -14,7 +14,7 @@ mod bitbucket_detector {
                 let (input, expected) = $value;
 
                 let pwd = "/pwd";
-                let detectors: Vec<Box<dyn Detector>> = vec![Box::new(BitBucketDetector)];
+                let detectors: Vec<Box<dyn Detector>> = vec![Box::new(BitBucketDetector::default())];
                 assert_eq!(expected, detect(input, pwd, &detectors).await.unwrap())
             }
         )*
@@ -28,4 +28,44 @@ mod bitbucket_detector {
         test_detect_2: ("bitbucket.org/hashicorp/tf-test-git.git",
             "git::https://bitbucket.org/hashicorp/tf-test-git.git"),
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_detect_tls() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // The API isn't reached when the TLS configuration is broken.
+        let detector = BitBucketDetector {
+            tls: getter::TlsConfig {
+                root_certificates: vec![std::path::PathBuf::from(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/testdata/tls/missing.pem"
+                ))],
+                ..getter::TlsConfig::default()
+            },
+            ..BitBucketDetector::default()
+        };
+
+        let err = detector
+            .detect("bitbucket.org/hashicorp/tf-test-git", "/pwd")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), getter::ErrorKind::Io);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_detect_cancelled() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // The API call is part of the download, so it is cancelled with it.
+        let ctx = getter::getter::Context::default();
+        ctx.cancel.cancel();
+
+        let err = BitBucketDetector::default()
+            .detect_with(&ctx, "bitbucket.org/hashicorp/tf-test-git", "/pwd")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), getter::ErrorKind::Cancelled);
+    }
 }
@@ -0,0 +1,240 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod checksum {
+    use super::common::serve;
+    use getter::{Client, ClientMode, ErrorKind};
+    use hyper::{Body, Response, StatusCode};
+    use std::fs;
+    use tempdir::TempDir;
+
+    const MD5: &str = "09f7e02f1290be211da707a266f153b3";
+    const SHA1: &str = "1d229271928d3f9e2bb0375bd6ce5db6c6d348d9";
+    const SHA256: &str = "66a045b452102c59d840ec097d59d9467e13a3f34f6494e539ffd32c1bb35f18";
+    const SHA512: &str = "c2bad2223811194582af4d1508ac02cd69eeeeedeeb98d54fcae4dcefb13cc882e7640328206603d3fb9cd5f949a9be0db054dd34fbfa190c498a5fe09750cef";
+
+    // get downloads "Hello\n" with the given checksum parameter into a
+    // fresh directory and returns the result together with the files left
+    // in that directory.
+    async fn get(checksum: &str) -> (getter::Result<()>, Vec<String>) {
+        let addr = serve(|req| {
+            // The checksum parameter must not be passed on to the server.
+            assert_eq!(req.uri().query(), None);
+            Response::new(Body::from("Hello\n"))
+        });
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        let result = Client::new(
+            format!("http://{}/file?checksum={}", addr, checksum),
+            &dst,
+            ClientMode::File,
+        )
+        .get()
+        .await;
+
+        if result.is_ok() {
+            assert_eq!(fs::read_to_string(&dst).unwrap(), "Hello\n");
+        }
+
+        let files = fs::read_dir(tmp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+
+        (result, files)
+    }
+
+    macro_rules! checksum_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[tokio::test]
+            async fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let (checksum, ok): (String, bool) = $value;
+
+                let (result, files) = get(&checksum).await;
+
+                if ok {
+                    result.unwrap();
+                    assert_eq!(files, ["file"]);
+                } else {
+                    assert!(result.is_err());
+                    assert!(files.is_empty(), "{:?}", files);
+                }
+            }
+        )*
+        }
+    }
+
+    checksum_tests! {
+        test_md5: (format!("md5:{}", MD5), true),
+        test_sha1: (format!("sha1:{}", SHA1), true),
+        test_sha256: (format!("sha256:{}", SHA256), true),
+        test_sha512: (format!("sha512:{}", SHA512), true),
+        test_sha256_uppercase: (format!("sha256:{}", SHA256.to_uppercase()), true),
+        test_guess_md5: (MD5.to_string(), true),
+        test_guess_sha512: (SHA512.to_string(), true),
+        test_mismatch: (format!("md5:{}", MD5.replace('0', "1")), false),
+        test_wrong_type: (format!("sha1:{}", MD5), false),
+        test_unknown_type: (format!("crc32:{}", MD5), false),
+        test_invalid_hex: ("md5:not-hex".to_string(), false),
+        test_unknown_length: ("abcdef".to_string(), false),
+    }
+
+    #[tokio::test]
+    async fn mismatch_error() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (result, _) = get(&format!("sha256:{}", SHA256.replace('6', "7"))).await;
+
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ChecksumMismatch);
+        assert_eq!(err.url().unwrap().path(), "/file");
+        assert!(err.to_string().starts_with("checksum mismatch"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn dir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let result = Client::new(
+            format!("git::https://127.0.0.1:1/repo?checksum=md5:{}", MD5),
+            &dst,
+            ClientMode::Dir,
+        )
+        .get()
+        .await;
+
+        assert!(result.is_err());
+        assert!(!dst.exists());
+    }
+
+    #[tokio::test]
+    async fn mismatch_keeps_existing() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let addr = serve(|_| Response::new(Body::from("Hello\n")));
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+        fs::write(&dst, "old").unwrap();
+
+        // The download is checked as it is received, so a bad file never
+        // replaces the one that is already there.
+        let err = Client::new(
+            format!(
+                "http://{}/file?checksum=md5:{}",
+                addr,
+                MD5.replace('0', "1")
+            ),
+            &dst,
+            ClientMode::File,
+        )
+        .get()
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::ChecksumMismatch);
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "old");
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn file_getter() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = tmp_dir.path().join("src");
+        fs::write(&src, "Hello\n").unwrap();
+        let dst = tmp_dir.path().join("dst");
+        fs::write(&dst, "old").unwrap();
+
+        // Files that aren't streamed are checked before they are copied
+        // into place as well, so the existing file survives a mismatch.
+        let get = |md5: String| {
+            Client::new(
+                format!("file://{}?checksum=md5:{}", src.display(), md5),
+                &dst,
+                ClientMode::File,
+            )
+        };
+
+        let err = get(MD5.replace('0', "1")).get().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ChecksumMismatch);
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "old");
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 2);
+
+        get(MD5.to_string()).get().await.unwrap();
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "Hello\n");
+    }
+
+    // get_with_sums downloads "Hello\n" using a checksum file on the same
+    // server with the given contents. If sums is None the checksum file is
+    // missing.
+    async fn get_with_sums(sums: Option<String>) -> getter::Result<()> {
+        let addr = serve(move |req| match (req.uri().path(), &sums) {
+            ("/file", _) => Response::new(Body::from("Hello\n")),
+            ("/SHA256SUMS", Some(sums)) => Response::new(Body::from(sums.clone())),
+            _ => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap(),
+        });
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        Client::new(
+            format!("http://{0}/file?checksum=file:http://{0}/SHA256SUMS", addr),
+            &dst,
+            ClientMode::File,
+        )
+        .get()
+        .await?;
+
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "Hello\n");
+        Ok(())
+    }
+
+    const OTHER: &str = "d9298a10d1b0735837dc4bd85dac641b0f3cef27a47e5d53a54f2f3f5b2fcffa";
+
+    macro_rules! sums_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[tokio::test]
+            async fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let (sums, ok): (Option<String>, bool) = $value;
+
+                let result = get_with_sums(sums).await;
+
+                if ok {
+                    result.unwrap();
+                } else {
+                    assert!(result.is_err());
+                }
+            }
+        )*
+        }
+    }
+
+    sums_tests! {
+        test_sums_gnu: (Some(format!("{}  other\n{}  file\n", OTHER, SHA256)), true),
+        test_sums_gnu_binary: (Some(format!("{} *file\n", SHA256)), true),
+        test_sums_gnu_relative: (Some(format!("{}  ./file\n", SHA256)), true),
+        test_sums_gnu_md5: (Some(format!("{}  file\n", MD5)), true),
+        test_sums_bsd: (Some(format!("SHA256 (other) = {}\nSHA256 (file) = {}\n", OTHER, SHA256)), true),
+        test_sums_bsd_sha512: (Some(format!("SHA512 (file) = {}\n", SHA512)), true),
+        test_sums_mismatch: (Some(format!("{}  file\n", OTHER)), false),
+        test_sums_not_listed: (Some(format!("{}  other\n", SHA256)), false),
+        test_sums_missing: (None, false),
+    }
+}
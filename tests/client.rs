@@ -0,0 +1,245 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod client {
+    use async_trait::async_trait;
+    use getter::getter::Context;
+    use getter::{Client, ClientMode, Detector, ErrorKind, Getter, Result};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tempdir::TempDir;
+    use url::Url;
+
+    // MockGetter writes a small, fixed tree for directories and the URL
+    // path for single files.
+    #[derive(Debug)]
+    struct MockGetter;
+
+    #[async_trait]
+    impl Getter for MockGetter {
+        async fn get(&self, _ctx: &Context, dst: &Path, _url: &Url) -> Result<()> {
+            fs::create_dir_all(dst.join("sub")).unwrap();
+            fs::write(dst.join("main.txt"), "main").unwrap();
+            fs::write(dst.join("sub").join("nested.txt"), "nested").unwrap();
+            Ok(())
+        }
+
+        async fn get_file(&self, _ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+            fs::write(dst, url.path()).unwrap();
+            Ok(())
+        }
+
+        fn client_mode(&self, url: &Url) -> Result<ClientMode> {
+            if url.path().ends_with('/') {
+                Ok(ClientMode::Dir)
+            } else {
+                Ok(ClientMode::File)
+            }
+        }
+    }
+
+    fn client(src: &str, dst: &Path, mode: ClientMode) -> Client {
+        let mut getters: BTreeMap<&'static str, Box<dyn Getter>> = BTreeMap::new();
+        getters.insert("mock", Box::new(MockGetter));
+
+        let mut client = Client::new(src, dst, mode);
+        client.getters = Arc::new(getters);
+        client
+    }
+
+    #[tokio::test]
+    async fn get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("nested").join("file.txt");
+
+        client("mock://host/foo.txt", &dst, ClientMode::File)
+            .get()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "/foo.txt");
+    }
+
+    #[tokio::test]
+    async fn get_dir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dir");
+
+        client("mock://host/repo", &dst, ClientMode::Dir)
+            .get()
+            .await
+            .unwrap();
+
+        assert!(dst.join("main.txt").is_file());
+        assert!(dst.join("sub").join("nested.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn get_dir_subdir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dir");
+
+        client("mock://host/repo//sub", &dst, ClientMode::Dir)
+            .get()
+            .await
+            .unwrap();
+
+        assert!(!dst.join("main.txt").exists());
+        assert_eq!(
+            fs::read_to_string(dst.join("nested.txt")).unwrap(),
+            "nested"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_dir_subdir_glob() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dir");
+
+        client("mock://host/repo//s*", &dst, ClientMode::Dir)
+            .get()
+            .await
+            .unwrap();
+
+        assert!(dst.join("nested.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn get_dir_subdir_missing() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dir");
+
+        let err = client("mock://host/repo//missing", &dst, ClientMode::Dir)
+            .get()
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.url().unwrap().as_str(), "mock://host/repo");
+    }
+
+    #[tokio::test]
+    async fn get_any_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        client("mock://host/foo/bar.txt", tmp_dir.path(), ClientMode::Any)
+            .get()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("bar.txt")).unwrap(),
+            "/foo/bar.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_any_dir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        client("mock://host/repo/", tmp_dir.path(), ClientMode::Any)
+            .get()
+            .await
+            .unwrap();
+
+        assert!(tmp_dir.path().join("main.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn get_forced() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file.txt");
+
+        client("mock::https://host/foo.txt", &dst, ClientMode::File)
+            .get()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "/foo.txt");
+    }
+
+    // MockDetector turns "mock" into a mock URL and records the connect
+    // timeout of the context it was given.
+    #[derive(Debug, Default)]
+    struct MockDetector {
+        connect: Arc<Mutex<Option<Duration>>>,
+    }
+
+    #[async_trait]
+    impl Detector for MockDetector {
+        async fn detect(&self, src: &str, pwd: &str) -> Result<(String, bool)> {
+            self.detect_with(&Context::default(), src, pwd).await
+        }
+
+        async fn detect_with(&self, ctx: &Context, src: &str, _: &str) -> Result<(String, bool)> {
+            *self.connect.lock().unwrap() = ctx.timeouts.connect;
+            Ok(("mock://host/foo.txt".to_string(), src == "mock"))
+        }
+    }
+
+    #[tokio::test]
+    async fn get_detect_context() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file.txt");
+
+        let detector = MockDetector::default();
+        let connect = detector.connect.clone();
+
+        let mut client = client("mock", &dst, ClientMode::File);
+        let detectors: Vec<Box<dyn Detector>> = vec![Box::new(detector)];
+        client.detectors = Arc::new(detectors);
+        client.timeouts.connect = Some(Duration::from_secs(7));
+        client.get().await.unwrap();
+
+        // Detectors see the settings of the download they are part of.
+        assert_eq!(*connect.lock().unwrap(), Some(Duration::from_secs(7)));
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "/foo.txt");
+    }
+
+    #[tokio::test]
+    async fn get_invalid_mode() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let result = client("mock://host/foo.txt", tmp_dir.path(), ClientMode::Invalid)
+            .get()
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn get_unsupported_scheme() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = client("nope://host/foo.txt", tmp_dir.path(), ClientMode::File)
+            .get()
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::UnsupportedScheme);
+        assert_eq!(err.url().unwrap().as_str(), "nope://host/foo.txt");
+    }
+}
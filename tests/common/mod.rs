@@ -0,0 +1,95 @@
+#![allow(dead_code)]
+
+use hyper::server::conn::Http;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use native_tls::Identity;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio_native_tls::TlsAcceptor;
+
+// serve starts an HTTP server on a random local port that answers every
+// request with the given handler, and returns the address it listens on.
+pub fn serve<F>(handler: F) -> SocketAddr
+where
+    F: Fn(Request<Body>) -> Response<Body> + Clone + Send + Sync + 'static,
+{
+    let make_svc = make_service_fn(move |_| {
+        let handler = handler.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let resp = handler(req);
+                async move { Ok::<_, Infallible>(resp) }
+            }))
+        }
+    });
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    addr
+}
+
+// serve_tls is like serve, but speaks HTTPS using the certificate for
+// localhost in testdata/tls, which is signed by testdata/tls/ca.pem.
+pub fn serve_tls<F>(handler: F) -> SocketAddr
+where
+    F: Fn(Request<Body>) -> Response<Body> + Clone + Send + Sync + 'static,
+{
+    let identity = Identity::from_pkcs8(
+        include_bytes!("../../testdata/tls/server.pem"),
+        include_bytes!("../../testdata/tls/server.key"),
+    )
+    .unwrap();
+    let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let listener = TcpListener::from_std(listener).unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                if let Ok(stream) = acceptor.accept(stream).await {
+                    let service = service_fn(move |req| {
+                        let resp = handler(req);
+                        async move { Ok::<_, Infallible>(resp) }
+                    });
+                    let _ = Http::new().serve_connection(stream, service).await;
+                }
+            });
+        }
+    });
+
+    addr
+}
+
+// serve_async is like serve, but with a handler that may wait, e.g. to read
+// the body of the request.
+pub fn serve_async<F, R>(handler: F) -> SocketAddr
+where
+    F: Fn(Request<Body>) -> R + Clone + Send + Sync + 'static,
+    R: Future<Output = Response<Body>> + Send + 'static,
+{
+    let make_svc = make_service_fn(move |_| {
+        let handler = handler.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let resp = handler(req);
+                async move { Ok::<_, Infallible>(resp.await) }
+            }))
+        }
+    });
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    addr
+}
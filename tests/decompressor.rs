@@ -0,0 +1,287 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod decompressor {
+    use getter::decompressor::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    fn testdata(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/decompress")
+            .join(name)
+    }
+
+    // list returns the relative paths of all files under dir, sorted.
+    fn list(dir: &Path) -> Vec<String> {
+        fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    walk(root, &path, files);
+                } else {
+                    let rel = path.strip_prefix(root).unwrap();
+                    files.push(rel.to_str().unwrap().replace('\\', "/"));
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        walk(dir, dir, &mut files);
+        files.sort();
+        files
+    }
+
+    macro_rules! dir_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let (decompressor, input, expected): (&dyn Decompressor, &str, Option<&[&str]>) = $value;
+
+                let tmp_dir = TempDir::new("rs-getter").unwrap();
+                let dst = tmp_dir.path().join("dst");
+
+                let result = decompressor.decompress(&dst, &testdata(input), true, None);
+
+                match expected {
+                    Some(files) => {
+                        result.unwrap();
+                        assert_eq!(list(&dst), files);
+                    }
+                    None => assert!(result.is_err()),
+                }
+            }
+        )*
+        }
+    }
+
+    macro_rules! file_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let (decompressor, input, expected): (&dyn Decompressor, &str, Option<&str>) = $value;
+
+                let tmp_dir = TempDir::new("rs-getter").unwrap();
+                let dst = tmp_dir.path().join("nested").join("file");
+
+                let result = decompressor.decompress(&dst, &testdata(input), false, None);
+
+                match expected {
+                    Some(contents) => {
+                        result.unwrap();
+                        assert_eq!(fs::read_to_string(&dst).unwrap(), contents);
+                    }
+                    None => assert!(result.is_err()),
+                }
+            }
+        )*
+        }
+    }
+
+    const MULTIPLE: Option<&[&str]> = Some(&["a/file1", "b/c/file2"]);
+
+    dir_tests! {
+        test_dir_tar: (&TarDecompressor, "multiple.tar", MULTIPLE),
+        test_dir_tar_single: (&TarDecompressor, "single.tar", Some(&["file"])),
+        test_dir_tar_traversal: (&TarDecompressor, "traversal.tar", None),
+        test_dir_tar_gz: (&TarGzipDecompressor, "multiple.tar.gz", MULTIPLE),
+        test_dir_tgz: (&TarGzipDecompressor, "multiple.tgz", MULTIPLE),
+        test_dir_tar_bz2: (&TarBzip2Decompressor, "multiple.tar.bz2", MULTIPLE),
+        test_dir_tbz2: (&TarBzip2Decompressor, "multiple.tbz2", MULTIPLE),
+        test_dir_tar_xz: (&TarXzDecompressor, "multiple.tar.xz", MULTIPLE),
+        test_dir_txz: (&TarXzDecompressor, "multiple.txz", MULTIPLE),
+        test_dir_zip: (&ZipDecompressor, "multiple.zip", MULTIPLE),
+        test_dir_zip_traversal: (&ZipDecompressor, "traversal.zip", None),
+        test_dir_gz: (&GzipDecompressor, "file.gz", None),
+        test_dir_bz2: (&Bzip2Decompressor, "file.bz2", None),
+        test_dir_xz: (&XzDecompressor, "file.xz", None),
+    }
+
+    file_tests! {
+        test_file_tar_single: (&TarDecompressor, "single.tar", Some("Hello\n")),
+        test_file_tar_multiple: (&TarDecompressor, "multiple.tar", None),
+        test_file_tar_gz_single: (&TarGzipDecompressor, "single.tar.gz", Some("Hello\n")),
+        test_file_tar_bz2_single: (&TarBzip2Decompressor, "single.tar.bz2", Some("Hello\n")),
+        test_file_tar_xz_single: (&TarXzDecompressor, "single.tar.xz", Some("Hello\n")),
+        test_file_zip_single: (&ZipDecompressor, "single.zip", Some("Hello\n")),
+        test_file_zip_multiple: (&ZipDecompressor, "multiple.zip", None),
+        test_file_gz: (&GzipDecompressor, "file.gz", Some("Hello\n")),
+        test_file_bz2: (&Bzip2Decompressor, "file.bz2", Some("Hello\n")),
+        test_file_xz: (&XzDecompressor, "file.xz", Some("Hello\n")),
+    }
+
+    mod client {
+        use super::*;
+        use async_trait::async_trait;
+        use getter::getter::Context;
+        use getter::{Client, ClientMode, ErrorKind, Getter, Result};
+        use std::collections::BTreeMap;
+        use std::sync::Arc;
+        use url::Url;
+
+        // TestdataGetter serves the files in testdata/decompress by their
+        // basename, or by the name query parameter if it is set.
+        #[derive(Debug)]
+        struct TestdataGetter;
+
+        #[async_trait]
+        impl Getter for TestdataGetter {
+            async fn get(&self, _ctx: &Context, _dst: &Path, url: &Url) -> Result<()> {
+                panic!("unexpected directory download of {}", url);
+            }
+
+            async fn get_file(&self, _ctx: &Context, dst: &Path, url: &Url) -> Result<()> {
+                assert!(!url.as_str().contains("archive="), "{}", url);
+
+                let name = match url.query_pairs().find(|(k, _)| k == "name") {
+                    Some((_, name)) => name.into_owned(),
+                    None => url
+                        .path_segments()
+                        .unwrap()
+                        .next_back()
+                        .unwrap()
+                        .to_string(),
+                };
+                fs::copy(testdata(&name), dst).unwrap();
+                Ok(())
+            }
+
+            fn client_mode(&self, _url: &Url) -> Result<ClientMode> {
+                Ok(ClientMode::File)
+            }
+        }
+
+        fn client(src: &str, dst: &Path, mode: ClientMode) -> Client {
+            let mut getters: BTreeMap<&'static str, Box<dyn Getter>> = BTreeMap::new();
+            getters.insert("testdata", Box::new(TestdataGetter));
+
+            let mut client = Client::new(src, dst, mode);
+            client.getters = Arc::new(getters);
+            client
+        }
+
+        #[tokio::test]
+        async fn get_dir() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("dst");
+
+            client("testdata://host/multiple.tar.gz", &dst, ClientMode::Dir)
+                .get()
+                .await
+                .unwrap();
+
+            assert_eq!(list(&dst), ["a/file1", "b/c/file2"]);
+        }
+
+        #[tokio::test]
+        async fn get_any() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("dst");
+
+            client("testdata://host/multiple.zip", &dst, ClientMode::Any)
+                .get()
+                .await
+                .unwrap();
+
+            assert_eq!(list(&dst), ["a/file1", "b/c/file2"]);
+        }
+
+        #[tokio::test]
+        async fn get_dir_subdir() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("dst");
+
+            client("testdata://host/multiple.tar.xz//b", &dst, ClientMode::Dir)
+                .get()
+                .await
+                .unwrap();
+
+            assert_eq!(list(&dst), ["c/file2"]);
+        }
+
+        #[tokio::test]
+        async fn get_file() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("file");
+
+            client("testdata://host/file.bz2", &dst, ClientMode::File)
+                .get()
+                .await
+                .unwrap();
+
+            assert_eq!(fs::read_to_string(&dst).unwrap(), "Hello\n");
+        }
+
+        #[tokio::test]
+        async fn get_archive_param() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("dst");
+
+            client(
+                "testdata://host/download?name=multiple.tgz&archive=tar.gz",
+                &dst,
+                ClientMode::Dir,
+            )
+            .get()
+            .await
+            .unwrap();
+
+            assert_eq!(list(&dst), ["a/file1", "b/c/file2"]);
+        }
+
+        #[tokio::test]
+        async fn get_archive_param_disabled() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("file");
+
+            client(
+                "testdata://host/multiple.tar.gz?archive=false",
+                &dst,
+                ClientMode::File,
+            )
+            .get()
+            .await
+            .unwrap();
+
+            assert_eq!(
+                fs::read(&dst).unwrap(),
+                fs::read(testdata("multiple.tar.gz")).unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn get_archive_param_unsupported() {
+            let _ = env_logger::builder().is_test(true).try_init();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("dst");
+
+            let result = client(
+                "testdata://host/multiple.tar.gz?archive=rar",
+                &dst,
+                ClientMode::Dir,
+            )
+            .get()
+            .await;
+
+            assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidSource);
+        }
+    }
+}
@@ -78,5 +78,25 @@ mod detect {
             "",
             "git::ssh://git@my.custom.git/dir1/dir2",
         ),
+        test_detect_13: (
+            "oci::ghcr.io/org/repo:v1.0",
+            "/opt",
+            "oci::oci://ghcr.io/org/repo:v1.0",
+        ),
+        test_detect_14: (
+            "oci::localhost:5000/repo@sha256:abcd?plain_http=true",
+            "/opt",
+            "oci::oci://localhost:5000/repo@sha256:abcd?plain_http=true",
+        ),
+        test_detect_15: (
+            "oci::registry.example.com:5000/org/repo:v1//sub",
+            "",
+            "oci::oci://registry.example.com:5000/org/repo:v1//sub",
+        ),
+        test_detect_16: (
+            "oci::oci://ghcr.io/org/repo",
+            "",
+            "oci::oci://ghcr.io/org/repo",
+        ),
     }
 }
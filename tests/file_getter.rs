@@ -0,0 +1,267 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod file_getter {
+    use getter::getter::{Context, FileGetter, Getter};
+    use getter::{Client, ClientMode, ErrorKind};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+    use url::Url;
+
+    // source creates a directory with a few files to get from.
+    fn source(tmp_dir: &TempDir) -> PathBuf {
+        let src = tmp_dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("main.tf"), "main").unwrap();
+        fs::write(src.join("sub").join("nested.tf"), "nested").unwrap();
+        src
+    }
+
+    fn url(path: &Path) -> Url {
+        Url::from_file_path(path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_symlink() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("nested").join("dst");
+
+        FileGetter::default()
+            .get(&Context::default(), &dst, &url(&src))
+            .await
+            .unwrap();
+
+        assert!(fs::symlink_metadata(&dst).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&dst).unwrap(), src);
+        assert_eq!(fs::read_to_string(dst.join("main.tf")).unwrap(), "main");
+    }
+
+    #[tokio::test]
+    async fn get_copy() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("dst");
+
+        FileGetter { copy: true }
+            .get(&Context::default(), &dst, &url(&src))
+            .await
+            .unwrap();
+
+        assert!(!fs::symlink_metadata(&dst).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(dst.join("main.tf")).unwrap(), "main");
+        assert_eq!(
+            fs::read_to_string(dst.join("sub").join("nested.tf")).unwrap(),
+            "nested"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn get_copy_dangling_symlink() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        std::os::unix::fs::symlink("missing.tf", src.join("dangling.tf")).unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        FileGetter { copy: true }
+            .get(&Context::default(), &dst, &url(&src))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_link(dst.join("dangling.tf")).unwrap(),
+            Path::new("missing.tf")
+        );
+        assert_eq!(fs::read_to_string(dst.join("main.tf")).unwrap(), "main");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn get_copy_cyclic_symlink() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        std::os::unix::fs::symlink("..", src.join("sub").join("parent")).unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        FileGetter { copy: true }
+            .get(&Context::default(), &dst, &url(&src))
+            .await
+            .unwrap();
+
+        let link = dst.join("sub").join("parent");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new(".."));
+        assert_eq!(
+            fs::read_to_string(link.join("sub").join("nested.tf")).unwrap(),
+            "nested"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_replaces_symlink() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("dst");
+
+        FileGetter::default()
+            .get(&Context::default(), &dst, &url(&src.join("sub")))
+            .await
+            .unwrap();
+        FileGetter::default()
+            .get(&Context::default(), &dst, &url(&src))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_link(&dst).unwrap(), src);
+    }
+
+    #[tokio::test]
+    async fn get_existing_dir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("dst");
+        fs::create_dir(&dst).unwrap();
+        fs::write(dst.join("keep"), "data").unwrap();
+
+        for getter in [FileGetter::default(), FileGetter { copy: true }] {
+            assert!(getter
+                .get(&Context::default(), &dst, &url(&src))
+                .await
+                .is_err());
+            assert_eq!(fs::read_to_string(dst.join("keep")).unwrap(), "data");
+        }
+    }
+
+    #[tokio::test]
+    async fn get_missing_source() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let src = url(&tmp_dir.path().join("missing"));
+        let err = FileGetter::default()
+            .get(&Context::default(), &dst, &src)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.url(), Some(&src));
+        assert!(!dst.exists());
+    }
+
+    #[tokio::test]
+    async fn get_file_source() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("dst");
+
+        let result = FileGetter::default()
+            .get(&Context::default(), &dst, &url(&src.join("main.tf")))
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("nested").join("file");
+
+        FileGetter::default()
+            .get_file(&Context::default(), &dst, &url(&src.join("main.tf")))
+            .await
+            .unwrap();
+
+        assert!(!fs::symlink_metadata(&dst).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "main");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn get_file_replaces_symlink() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("file");
+        std::os::unix::fs::symlink(src.join("main.tf"), &dst).unwrap();
+
+        FileGetter::default()
+            .get_file(
+                &Context::default(),
+                &dst,
+                &url(&src.join("sub").join("nested.tf")),
+            )
+            .await
+            .unwrap();
+
+        // The file the symlink pointed to must be left alone.
+        assert_eq!(fs::read_to_string(src.join("main.tf")).unwrap(), "main");
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "nested");
+    }
+
+    #[tokio::test]
+    async fn get_file_dir_source() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("file");
+
+        assert!(FileGetter::default()
+            .get_file(&Context::default(), &dst, &url(&src))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn client_mode() {
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+
+        let getter = FileGetter::default();
+        assert_eq!(getter.client_mode(&url(&src)).unwrap(), ClientMode::Dir);
+        assert_eq!(
+            getter.client_mode(&url(&src.join("main.tf"))).unwrap(),
+            ClientMode::File
+        );
+        assert!(getter.client_mode(&url(&src.join("missing"))).is_err());
+    }
+
+    #[tokio::test]
+    async fn client_get_relative() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = source(&tmp_dir);
+        let dst = tmp_dir.path().join("dst");
+
+        let mut client = Client::new("./src", &dst, ClientMode::Any);
+        client.pwd = tmp_dir.path().to_str().unwrap().to_string();
+        client.get().await.unwrap();
+
+        assert_eq!(fs::read_link(&dst).unwrap(), src);
+        assert_eq!(fs::read_to_string(dst.join("main.tf")).unwrap(), "main");
+    }
+}
@@ -0,0 +1,57 @@
+mod gcs_detector {
+    use getter::{
+        detect,
+        detector::{Detector, GCSDetector},
+        ErrorKind,
+    };
+
+    macro_rules! detect_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            # [tokio::test]
+            async fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let (input, expected) = $value;
+
+                let pwd = "/pwd";
+                let detectors: Vec<Box<dyn Detector>> = vec![Box::new(GCSDetector)];
+                assert_eq !(expected, detect(input, pwd, &detectors).await.unwrap())
+            }
+        )*
+        }
+    }
+
+    detect_tests! {
+        test_detect_1: ("www.googleapis.com/storage/v1/bucket/foo",
+        "gcs::https://www.googleapis.com/storage/v1/bucket/foo"),
+        test_detect_2: ("www.googleapis.com/storage/v1/bucket/foo/bar",
+        "gcs::https://www.googleapis.com/storage/v1/bucket/foo/bar"),
+        test_detect_3: ("www.googleapis.com/storage/v1/foo/bar.baz",
+        "gcs::https://www.googleapis.com/storage/v1/foo/bar.baz"),
+        test_detect_4: ("www.googleapis.com/storage/v2/foo/bar/toor.baz",
+        "gcs::https://www.googleapis.com/storage/v2/foo/bar/toor.baz"),
+        test_detect_5: ("www.googleapis.com/storage/v1/bucket/foo//bar",
+        "gcs::https://www.googleapis.com/storage/v1/bucket/foo//bar"),
+        test_detect_6: ("www.googleapis.com/storage/v1/bucket/foo?generation=1",
+        "gcs::https://www.googleapis.com/storage/v1/bucket/foo?generation=1"),
+    }
+
+    #[tokio::test]
+    async fn test_detect_other() {
+        let (_, ok) = GCSDetector
+            .detect("github.com/foo/bar", "/pwd")
+            .await
+            .unwrap();
+        assert!(!ok);
+    }
+
+    #[tokio::test]
+    async fn test_detect_invalid() {
+        let err = GCSDetector
+            .detect("www.googleapis.com/storage/v1", "/pwd")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidSource);
+    }
+}
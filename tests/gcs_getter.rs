@@ -0,0 +1,560 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod gcs_getter {
+    use super::common::serve_async;
+    use getter::getter::{Context, GCSGetter, Getter, GETTERS};
+    use getter::{Client, ClientMode, ErrorKind};
+    use hyper::header::AUTHORIZATION;
+    use hyper::{Body, Method, Request, Response, StatusCode};
+    use rsa::pkcs1v15::{Signature, VerifyingKey};
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::signature::Verifier;
+    use rsa::RsaPrivateKey;
+    use sha2::Sha256;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use tempdir::TempDir;
+    use url::Url;
+
+    // KEY is the private key of the service account, which Storage checks
+    // the signatures of its JWTs with.
+    const KEY: &str = include_str!("../testdata/gcs/key.pem");
+
+    // KEY_PKCS1 is KEY in the PKCS#1 format that older tools write.
+    const KEY_PKCS1: &str = include_str!("../testdata/gcs/key-pkcs1.pem");
+
+    // Storage serves the objects of a single bucket called "bucket" the way
+    // the GCS JSON API does, listing one object per page. It also hands out
+    // access tokens for service accounts signed with its key, and records
+    // the requests it receives.
+    #[derive(Clone)]
+    struct Storage {
+        objects: Arc<BTreeMap<String, String>>,
+        key: Arc<RsaPrivateKey>,
+        requests: Arc<Mutex<Vec<Recorded>>>,
+    }
+
+    #[derive(Debug)]
+    struct Recorded {
+        uri: String,
+        authorization: Option<String>,
+    }
+
+    impl Storage {
+        fn new(objects: &[(&str, &str)]) -> Storage {
+            Storage {
+                objects: Arc::new(
+                    objects
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                ),
+                key: Arc::new(RsaPrivateKey::from_pkcs8_pem(KEY).unwrap()),
+                requests: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+
+        fn serve(&self) -> SocketAddr {
+            let storage = self.clone();
+            serve_async(move |req| {
+                let storage = storage.clone();
+                async move { storage.handle(req).await }
+            })
+        }
+
+        // getter returns a getter that sends its requests to the server at
+        // addr, authorized with a fixed token.
+        fn getter(addr: SocketAddr) -> GCSGetter {
+            GCSGetter {
+                endpoint: Some(Url::parse(&format!("http://{}", addr)).unwrap()),
+                access_token: Some("token".to_string()),
+                ..GCSGetter::default()
+            }
+        }
+
+        async fn handle(&self, req: Request<Body>) -> Response<Body> {
+            let path = req.uri().path().to_string();
+            let query: BTreeMap<String, String> =
+                url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+                    .into_owned()
+                    .collect();
+            self.requests.lock().unwrap().push(Recorded {
+                uri: req.uri().to_string(),
+                authorization: req
+                    .headers()
+                    .get(AUTHORIZATION)
+                    .map(|v| v.to_str().unwrap().to_string()),
+            });
+
+            if req.method() == Method::POST && path == "/token" {
+                let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                return self.token(&body);
+            }
+
+            if path == "/storage/v1/b/bucket/o" {
+                return self.list(&query);
+            }
+
+            let name = match path.strip_prefix("/storage/v1/b/bucket/o/") {
+                Some(name) => percent_encoding::percent_decode_str(name)
+                    .decode_utf8_lossy()
+                    .into_owned(),
+                None => return error(StatusCode::NOT_FOUND, "Not Found"),
+            };
+            assert_eq!(query.get("alt").map(String::as_str), Some("media"));
+
+            if name.starts_with("denied") {
+                return error(StatusCode::FORBIDDEN, "Access denied.");
+            }
+            match self.objects.get(&name) {
+                Some(data) => Response::new(Body::from(data.clone())),
+                None => error(
+                    StatusCode::NOT_FOUND,
+                    &format!("No such object: bucket/{}", name),
+                ),
+            }
+        }
+
+        fn list(&self, query: &BTreeMap<String, String>) -> Response<Body> {
+            let prefix = query.get("prefix").cloned().unwrap_or_default();
+            let after = query.get("pageToken").cloned().unwrap_or_default();
+            let mut names = self
+                .objects
+                .keys()
+                .filter(|k| k.starts_with(&prefix) && **k > after);
+
+            let mut list = serde_json::json!({ "kind": "storage#objects" });
+            if let Some(name) = names.next() {
+                list["items"] = serde_json::json!([{ "kind": "storage#object", "name": name }]);
+                if names.next().is_some() {
+                    list["nextPageToken"] = serde_json::json!(name);
+                }
+            }
+
+            Response::new(Body::from(list.to_string()))
+        }
+
+        // token checks the JWT assertion in the body and returns an access
+        // token for it.
+        fn token(&self, body: &[u8]) -> Response<Body> {
+            let form: BTreeMap<String, String> =
+                url::form_urlencoded::parse(body).into_owned().collect();
+            assert_eq!(
+                form["grant_type"],
+                "urn:ietf:params:oauth:grant-type:jwt-bearer"
+            );
+
+            let (message, signature) = form["assertion"].rsplit_once('.').unwrap();
+            let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).unwrap();
+            let verifier = VerifyingKey::<Sha256>::new(self.key.to_public_key());
+            let signature = Signature::try_from(signature.as_slice()).unwrap();
+            if verifier.verify(message.as_bytes(), &signature).is_err() {
+                return Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(r#"{"error":"invalid_grant"}"#))
+                    .unwrap();
+            }
+
+            let claims = message.split('.').nth(1).unwrap();
+            let claims: serde_json::Value = serde_json::from_slice(
+                &base64::decode_config(claims, base64::URL_SAFE_NO_PAD).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(claims["iss"], "getter@example.iam.gserviceaccount.com");
+            assert!(claims["aud"].as_str().unwrap().ends_with("/token"));
+            assert_eq!(
+                claims["scope"],
+                "https://www.googleapis.com/auth/devstorage.read_only"
+            );
+
+            Response::new(Body::from(
+                r#"{"access_token":"sa-token","expires_in":3599,"token_type":"Bearer"}"#,
+            ))
+        }
+
+        // service_account writes a key file with the given private key for
+        // a service account whose tokens are obtained from the server at
+        // addr.
+        fn service_account(
+            &self,
+            dir: &TempDir,
+            addr: SocketAddr,
+            kind: &str,
+            key: &str,
+        ) -> std::path::PathBuf {
+            let account = serde_json::json!({
+                "type": kind,
+                "project_id": "getter",
+                "private_key_id": "key-id",
+                "private_key": key,
+                "client_email": "getter@example.iam.gserviceaccount.com",
+                "token_uri": format!("http://{}/token", addr),
+            });
+
+            let path = dir.path().join("credentials.json");
+            fs::write(&path, account.to_string()).unwrap();
+            path
+        }
+
+        fn requests(&self) -> std::sync::MutexGuard<'_, Vec<Recorded>> {
+            self.requests.lock().unwrap()
+        }
+    }
+
+    fn error(status: StatusCode, message: &str) -> Response<Body> {
+        let body = serde_json::json!({
+            "error": { "code": status.as_u16(), "message": message },
+        });
+        Response::builder()
+            .status(status)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn gcs_url(path: &str) -> Url {
+        Url::parse(&format!("https://www.googleapis.com/storage/v1/{}", path)).unwrap()
+    }
+
+    #[test]
+    fn registered() {
+        assert!(GETTERS.contains_key("gcs"));
+    }
+
+    #[test]
+    fn debug_redacts_token() {
+        let getter = GCSGetter {
+            access_token: Some("secret-token".to_string()),
+            ..GCSGetter::default()
+        };
+
+        assert!(!format!("{:?}", getter).contains("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[("dir/hello world.txt", "Hello\n")]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        Storage::getter(addr)
+            .get_file(
+                &Context::default(),
+                &dst,
+                &gcs_url("bucket/dir/hello%20world.txt"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "Hello\n");
+
+        let requests = storage.requests();
+        assert_eq!(
+            requests[0].uri,
+            "/storage/v1/b/bucket/o/dir%2Fhello%20world.txt?alt=media"
+        );
+        assert_eq!(requests[0].authorization.as_deref(), Some("Bearer token"));
+    }
+
+    #[tokio::test]
+    async fn get_file_generation() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[("file", "Hello\n")]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        Storage::getter(addr)
+            .get_file(
+                &Context::default(),
+                &tmp_dir.path().join("file"),
+                &gcs_url("bucket/file?generation=42"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.requests()[0].uri,
+            "/storage/v1/b/bucket/o/file?alt=media&generation=42"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_dir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[
+            ("dir/a.txt", "a"),
+            ("dir/empty/", ""),
+            ("dir/sub/b.txt", "b"),
+            ("dirx/c.txt", "c"),
+        ]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        Storage::getter(addr)
+            .get(&Context::default(), &dst, &gcs_url("bucket/dir"))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dst.join("sub/b.txt")).unwrap(), "b");
+        assert!(!dst.join("empty").exists());
+        assert!(!dst.join("c.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn get_dir_outside() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[("dir/../evil", "evil")]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = Storage::getter(addr)
+            .get(
+                &Context::default(),
+                &tmp_dir.path().join("dst"),
+                &gcs_url("bucket/dir/"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidSource);
+        assert!(!tmp_dir.path().join("evil").exists());
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = Storage::getter(addr)
+            .get_file(
+                &Context::default(),
+                &tmp_dir.path().join("file"),
+                &gcs_url("bucket/missing"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(
+            err.to_string().contains("No such object: bucket/missing"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn access_denied() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = Storage::getter(addr)
+            .get_file(
+                &Context::default(),
+                &tmp_dir.path().join("file"),
+                &gcs_url("bucket/denied"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Authentication);
+    }
+
+    #[tokio::test]
+    async fn service_account() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        for key in [KEY, KEY_PKCS1] {
+            let storage = Storage::new(&[("file", "Hello\n")]);
+            let addr = storage.serve();
+
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let getter = GCSGetter {
+                access_token: None,
+                credentials_file: Some(storage.service_account(
+                    &tmp_dir,
+                    addr,
+                    "service_account",
+                    key,
+                )),
+                ..Storage::getter(addr)
+            };
+
+            getter
+                .get_file(
+                    &Context::default(),
+                    &tmp_dir.path().join("file"),
+                    &gcs_url("bucket/file"),
+                )
+                .await
+                .unwrap();
+
+            let requests = storage.requests();
+            assert_eq!(requests[0].uri, "/token");
+            assert_eq!(
+                requests[1].authorization.as_deref(),
+                Some("Bearer sa-token")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn service_account_unsupported() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[("file", "Hello\n")]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let getter = GCSGetter {
+            access_token: None,
+            credentials_file: Some(storage.service_account(&tmp_dir, addr, "authorized_user", KEY)),
+            ..Storage::getter(addr)
+        };
+
+        let err = getter
+            .get_file(
+                &Context::default(),
+                &tmp_dir.path().join("file"),
+                &gcs_url("bucket/file"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Authentication);
+        assert!(storage.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn client() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let storage = Storage::new(&[("dir/a.txt", "a"), ("dir/b.txt", "b")]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut client = Client::new(
+            "www.googleapis.com/storage/v1/bucket/dir/",
+            &dst,
+            ClientMode::Any,
+        );
+        let mut getters = BTreeMap::new();
+        getters.insert("gcs", Box::new(Storage::getter(addr)) as Box<dyn Getter>);
+        client.getters = Arc::new(getters);
+        client.get().await.unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dst.join("b.txt")).unwrap(), "b");
+    }
+
+    // The settings from the environment are tested in a single test, so
+    // that the tests don't change the environment from under each other.
+    #[tokio::test]
+    async fn environment() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        std::env::remove_var("GOOGLE_OAUTH_ACCESS_TOKEN");
+        std::env::remove_var("GOOGLE_APPLICATION_CREDENTIALS");
+        std::env::remove_var("STORAGE_EMULATOR_HOST");
+
+        let storage = Storage::new(&[("file", "Hello\n")]);
+        let addr = storage.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+        let get = |url: Url| {
+            let dst = dst.clone();
+            async move {
+                GCSGetter::default()
+                    .get_file(&Context::default(), &dst, &url)
+                    .await
+                    .unwrap();
+            }
+        };
+        let local_url = Url::parse(&format!("http://{}/storage/v1/bucket/file", addr)).unwrap();
+
+        // Without an endpoint the requests go to the host of the URL, and
+        // without any credentials they aren't authorized.
+        get(local_url.clone()).await;
+
+        std::env::set_var("GOOGLE_OAUTH_ACCESS_TOKEN", "env-token");
+        get(local_url).await;
+
+        std::env::remove_var("GOOGLE_OAUTH_ACCESS_TOKEN");
+        std::env::set_var(
+            "GOOGLE_APPLICATION_CREDENTIALS",
+            storage.service_account(&tmp_dir, addr, "service_account", KEY),
+        );
+        std::env::set_var("STORAGE_EMULATOR_HOST", addr.to_string());
+        get(gcs_url("bucket/file")).await;
+
+        std::env::remove_var("GOOGLE_APPLICATION_CREDENTIALS");
+        std::env::remove_var("STORAGE_EMULATOR_HOST");
+
+        let authorization: Vec<_> = storage
+            .requests()
+            .iter()
+            .map(|r| r.authorization.clone())
+            .collect();
+        assert_eq!(
+            authorization,
+            [
+                None,
+                Some("Bearer env-token".to_string()),
+                None,
+                Some("Bearer sa-token".to_string()),
+            ]
+        );
+    }
+
+    macro_rules! invalid_url_tests {
+        ($($name:ident: $url:expr,)*) => {
+        $(
+            #[tokio::test]
+            async fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let tmp_dir = TempDir::new("rs-getter").unwrap();
+                let url = Url::parse($url).unwrap();
+
+                let err = GCSGetter::default()
+                    .get_file(&Context::default(), &tmp_dir.path().join("file"), &url)
+                    .await
+                    .unwrap_err();
+
+                assert_eq!(err.kind(), ErrorKind::InvalidSource);
+            }
+        )*
+        }
+    }
+
+    invalid_url_tests! {
+        invalid_no_storage: "http://127.0.0.1:1/bucket/file",
+        invalid_no_bucket: "http://127.0.0.1:1/storage/v1/",
+        invalid_no_object: "http://127.0.0.1:1/storage/v1/bucket",
+    }
+}
@@ -0,0 +1,540 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod git_getter {
+    use super::common::serve;
+    use getter::getter::{Context, Getter, GitGetter};
+    use getter::{Client, ClientMode, ErrorKind};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::Once;
+    use tempdir::TempDir;
+    use url::Url;
+
+    // TestRepo is a bare repository to download from, along with a working
+    // copy that is used to push commits into it.
+    struct TestRepo {
+        _dir: TempDir,
+        bare: PathBuf,
+        work: PathBuf,
+    }
+
+    impl TestRepo {
+        fn new() -> TestRepo {
+            static SETUP: Once = Once::new();
+            SETUP.call_once(|| {
+                // Submodules in these tests are cloned over the file
+                // protocol, which git no longer allows by default.
+                std::env::set_var("GIT_CONFIG_COUNT", "1");
+                std::env::set_var("GIT_CONFIG_KEY_0", "protocol.file.allow");
+                std::env::set_var("GIT_CONFIG_VALUE_0", "always");
+            });
+
+            let dir = TempDir::new("rs-getter").unwrap();
+            let bare = dir.path().join("repo.git");
+            let work = dir.path().join("work");
+
+            git(
+                dir.path(),
+                &["init", "--bare", "--initial-branch=master", "repo.git"],
+            );
+            git(dir.path(), &["init", "--initial-branch=master", "work"]);
+            git(&work, &["remote", "add", "origin", bare.to_str().unwrap()]);
+
+            TestRepo {
+                _dir: dir,
+                bare,
+                work,
+            }
+        }
+
+        fn git(&self, args: &[&str]) -> String {
+            git(&self.work, args)
+        }
+
+        fn commit(&self, file: &str, contents: &str) -> String {
+            let path = self.work.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+
+            self.git(&["add", "-A"]);
+            self.git(&["commit", "-m", file]);
+            self.git(&["push", "--all", "origin"]);
+            self.git(&["rev-parse", "HEAD"]).trim().to_string()
+        }
+
+        fn url(&self) -> Url {
+            Url::from_file_path(&self.bare).unwrap()
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", "rs-getter")
+            .env("GIT_AUTHOR_EMAIL", "rs-getter@example.com")
+            .env("GIT_COMMITTER_NAME", "rs-getter")
+            .env("GIT_COMMITTER_EMAIL", "rs-getter@example.com")
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        GitGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "main");
+    }
+
+    #[tokio::test]
+    async fn get_branch() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+        repo.git(&["checkout", "-b", "test-branch"]);
+        repo.commit("branch.txt", "branch");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=test-branch"));
+        GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert!(dst.join("branch.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn get_tag() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+        repo.git(&["tag", "v1.0"]);
+        repo.commit("main.txt", "v2");
+        repo.git(&["push", "--tags", "origin"]);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=v1.0"));
+        GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v1");
+    }
+
+    #[tokio::test]
+    async fn get_commit() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        let commit = repo.commit("main.txt", "v1");
+        repo.commit("main.txt", "v2");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some(&format!("ref={}", commit)));
+        GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v1");
+    }
+
+    #[tokio::test]
+    async fn get_shallow() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+        repo.commit("main.txt", "v2");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some("depth=1"));
+        GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v2");
+        assert_eq!(git(&dst, &["rev-list", "--count", "HEAD"]).trim(), "1");
+    }
+
+    #[tokio::test]
+    async fn get_missing_ref() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=missing"));
+        let err = GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.url(), Some(&url));
+    }
+
+    #[tokio::test]
+    async fn get_option_ref() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+
+        // Refs that look like options are rejected before git sees them,
+        // whether they are checked out or cloned directly.
+        for query in ["ref=--orphan=evil", "ref=-b&depth=1"] {
+            let tmp_dir = TempDir::new("rs-getter").unwrap();
+            let dst = tmp_dir.path().join("dst");
+
+            let mut url = repo.url();
+            url.set_query(Some(query));
+            let err = GitGetter
+                .get(&Context::default(), &dst, &url)
+                .await
+                .unwrap_err();
+
+            assert_eq!(err.kind(), ErrorKind::InvalidSource);
+            assert!(!dst.exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn get_ref_named_like_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+        repo.git(&["checkout", "-b", "main.txt"]);
+        repo.commit("branch.txt", "branch");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=main.txt"));
+        GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert!(dst.join("branch.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn get_shallow_commit() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        let commit = repo.commit("main.txt", "v1");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some(&format!("ref={}&depth=1", commit)));
+        let err = GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("requires 'ref' to be a branch or tag name"));
+    }
+
+    #[tokio::test]
+    async fn get_update() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        GitGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v1");
+
+        repo.commit("main.txt", "v2");
+
+        GitGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v2");
+    }
+
+    #[tokio::test]
+    async fn get_update_ref() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+        repo.git(&["tag", "v1.0"]);
+        repo.git(&["push", "--tags", "origin"]);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        GitGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+
+        repo.commit("main.txt", "v2");
+        repo.git(&["tag", "v2.0"]);
+        repo.git(&["push", "--tags", "origin"]);
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=v2.0"));
+        GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v2");
+
+        url.set_query(Some("ref=v1.0"));
+        GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v1");
+    }
+
+    #[tokio::test]
+    async fn get_submodules() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let child = TestRepo::new();
+        child.commit("child.txt", "child");
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+        repo.git(&["submodule", "add", child.url().as_str(), "child"]);
+        repo.git(&["commit", "-m", "add submodule"]);
+        repo.git(&["push", "--all", "origin"]);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        GitGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dst.join("child").join("child.txt")).unwrap(),
+            "child"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+        repo.commit("sub/file.txt", "file");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file.txt");
+
+        let mut url = repo.url();
+        url.set_path(&format!("{}/main.txt", url.path()));
+        GitGetter
+            .get_file(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "main");
+    }
+
+    #[tokio::test]
+    async fn client_get_subdir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+        repo.commit("sub/file.txt", "file");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let src = format!("git::{}//sub", repo.url());
+        Client::new(src, &dst, ClientMode::Dir).get().await.unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("file.txt")).unwrap(), "file");
+        assert!(!dst.join("main.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn get_ssh_key() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // Stand in for ssh with a script that records how it was called and
+        // what the key file it was given looked like.
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let log = tmp_dir.path().join("ssh.log");
+        let script = tmp_dir.path().join("fake-ssh");
+        fs::write(
+            &script,
+            format!(
+                r#"#!/bin/sh
+key=""
+prev=""
+for arg in "$@"; do
+    if [ "$prev" = "-i" ]; then key="$arg"; fi
+    prev="$arg"
+done
+{{
+    echo "args: $*"
+    echo "mode: $(stat -c %a "$key" 2>/dev/null || stat -f %Lp "$key")"
+    echo "key: $(cat "$key")"
+}} >> '{}'
+exit 1
+"#,
+                log.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        std::env::set_var("GIT_SSH_COMMAND", &script);
+
+        let url = Url::parse(&format!(
+            "ssh://git@example.com/repo.git?sshkey={}",
+            base64::encode("not a real key")
+        ))
+        .unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        assert!(GitGetter
+            .get(&Context::default(), &dst, &url)
+            .await
+            .is_err());
+
+        let log = fs::read_to_string(&log).unwrap();
+        assert!(log.contains("IdentitiesOnly=yes"), "{}", log);
+        assert!(log.contains("mode: 600"), "{}", log);
+        assert!(log.contains("key: not a real key"), "{}", log);
+        assert!(!log.contains("sshkey="), "{}", log);
+
+        // The key file is removed once the getter is done.
+        let key_file = log
+            .split_whitespace()
+            .skip_while(|arg| *arg != "-i")
+            .nth(1)
+            .unwrap();
+        assert!(!Path::new(key_file).exists());
+    }
+
+    #[tokio::test]
+    async fn get_ssh_key_invalid() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let url = Url::parse("ssh://git@example.com/repo.git?sshkey=%%%").unwrap();
+
+        let err = GitGetter
+            .get(&Context::default(), &tmp_dir.path().join("dst"), &url)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidSource);
+        // The key must not end up in the error.
+        assert_eq!(err.url().unwrap().query(), Some("sshkey=redacted"));
+    }
+
+    #[tokio::test]
+    async fn get_http_netrc() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+        git(&repo.bare, &["update-server-info"]);
+
+        // Serve the repository over git's dumb HTTP protocol, but only to
+        // clients that authenticate.
+        let bare = repo.bare.clone();
+        let addr = serve(move |req| {
+            let expected = format!("Basic {}", base64::encode("user:secret"));
+            if req.headers().get(hyper::header::AUTHORIZATION) != Some(&expected.parse().unwrap()) {
+                return hyper::Response::builder()
+                    .status(hyper::StatusCode::UNAUTHORIZED)
+                    .header("WWW-Authenticate", "Basic realm=\"git\"")
+                    .body(hyper::Body::empty())
+                    .unwrap();
+            }
+            match fs::read(bare.join(req.uri().path().trim_start_matches('/'))) {
+                Ok(contents) => hyper::Response::new(hyper::Body::from(contents)),
+                Err(_) => hyper::Response::builder()
+                    .status(hyper::StatusCode::NOT_FOUND)
+                    .body(hyper::Body::empty())
+                    .unwrap(),
+            }
+        });
+        let url = Url::parse(&format!("http://127.0.0.1:{}/", addr.port())).unwrap();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let netrc = tmp_dir.path().join("netrc");
+        let dst = tmp_dir.path().join("dst");
+
+        fs::write(&netrc, "machine 127.0.0.1 login user password secret\n").unwrap();
+
+        let mut ctx = Context::default();
+        ctx.netrc = Some(netrc);
+        GitGetter.get(&ctx, &dst, &url).await.unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "main");
+    }
+}
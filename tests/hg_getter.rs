@@ -0,0 +1,284 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod hg_getter {
+    use getter::getter::{Context, Getter, HgGetter, GETTERS};
+    use getter::{Client, ClientMode, ErrorKind};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use tempdir::TempDir;
+    use url::Url;
+
+    // TestRepo is a repository to download from.
+    struct TestRepo {
+        _dir: TempDir,
+        path: PathBuf,
+    }
+
+    impl TestRepo {
+        // new creates an empty repository. The tests that use it only run
+        // with the hg-tests feature, and hg must be installed for them.
+        fn new() -> TestRepo {
+            let dir = TempDir::new("rs-getter").unwrap();
+            let path = dir.path().join("repo");
+
+            hg(dir.path(), &["init", "repo"]);
+
+            TestRepo { _dir: dir, path }
+        }
+
+        fn hg(&self, args: &[&str]) -> String {
+            hg(&self.path, args)
+        }
+
+        fn commit(&self, file: &str, contents: &str) {
+            let path = self.path.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+
+            self.hg(&["commit", "-A", "-m", file]);
+        }
+
+        fn url(&self) -> Url {
+            Url::from_file_path(&self.path).unwrap()
+        }
+    }
+
+    fn hg(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("hg")
+            .args(args)
+            .current_dir(dir)
+            .env("HGPLAIN", "1")
+            .env("HGUSER", "rs-getter <rs-getter@example.com>")
+            .output()
+            .expect("hg must be installed to run these tests");
+
+        assert!(
+            output.status.success(),
+            "hg {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn registered() {
+        assert!(GETTERS.contains_key("hg"));
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn get() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        HgGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "main");
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn get_branch() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+        repo.hg(&["branch", "test-branch"]);
+        repo.commit("branch.txt", "branch");
+        repo.hg(&["update", "default"]);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=test-branch"));
+        HgGetter.get(&Context::default(), &dst, &url).await.unwrap();
+
+        assert!(dst.join("branch.txt").is_file());
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn get_tag() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+        repo.hg(&["tag", "v1.0"]);
+        repo.commit("main.txt", "v2");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=v1.0"));
+        HgGetter.get(&Context::default(), &dst, &url).await.unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v1");
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn get_revision() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+        let rev = repo.hg(&["id", "-i"]).trim().to_string();
+        repo.commit("main.txt", "v2");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let mut url = repo.url();
+        url.set_query(Some(&format!("ref={}", rev)));
+        HgGetter.get(&Context::default(), &dst, &url).await.unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v1");
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn get_update() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "v1");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        HgGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+
+        repo.commit("main.txt", "v2");
+        HgGetter
+            .get(&Context::default(), &dst, &repo.url())
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("main.txt")).unwrap(), "v2");
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn get_missing_ref() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let mut url = repo.url();
+        url.set_query(Some("ref=missing"));
+        let err = HgGetter
+            .get(&Context::default(), &tmp_dir.path().join("dst"), &url)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("file.txt", "file");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file.txt");
+
+        let url = Url::parse(&format!("{}/file.txt", repo.url())).unwrap();
+        HgGetter
+            .get_file(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "file");
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "hg-tests"),
+        ignore = "requires hg, enable the hg-tests feature"
+    )]
+    async fn client_forced() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let repo = TestRepo::new();
+        repo.commit("main.txt", "main");
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        Client::new(format!("hg::{}", repo.url()), &dst, ClientMode::Dir)
+            .get()
+            .await
+            .unwrap();
+
+        assert!(dst.join("main.txt").is_file());
+    }
+
+    #[tokio::test]
+    async fn get_option_ref() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let hook = tmp_dir.path().join("hook");
+
+        // Refs that look like options are rejected before hg runs, so this
+        // doesn't need hg or a repository.
+        let mut url = Url::from_file_path(tmp_dir.path().join("repo")).unwrap();
+        for rev in [
+            format!("--config=hooks.update=touch {}", hook.display()),
+            "-r0".to_string(),
+        ] {
+            url.query_pairs_mut().clear().append_pair("ref", &rev);
+            let dst = tmp_dir.path().join("dst");
+            let err = HgGetter
+                .get(&Context::default(), &dst, &url)
+                .await
+                .unwrap_err();
+
+            assert_eq!(err.kind(), ErrorKind::InvalidSource);
+            assert!(!dst.exists());
+            assert!(!hook.exists());
+        }
+    }
+}
@@ -0,0 +1,54 @@
+mod oci_detector {
+    use getter::{
+        detect,
+        detector::{Detector, OCIDetector},
+    };
+
+    macro_rules! detect_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            # [tokio::test]
+            async fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let (input, expected) = $value;
+
+                let pwd = "/pwd";
+                let detectors: Vec<Box<dyn Detector>> = vec![Box::new(OCIDetector)];
+                assert_eq !(expected, detect(input, pwd, &detectors).await.unwrap())
+            }
+        )*
+        }
+    }
+
+    detect_tests! {
+        test_detect_1: ("ghcr.io/org/repo:v1",
+        "oci::oci://ghcr.io/org/repo:v1"),
+        test_detect_2: ("ghcr.io/org/repo//sub",
+        "oci::oci://ghcr.io/org/repo//sub"),
+        test_detect_3: ("oci::registry:5000/repo:v1",
+        "oci::oci://registry:5000/repo:v1"),
+        test_detect_4: ("oci::oci://registry:5000/repo:v1",
+        "oci::oci://registry:5000/repo:v1"),
+    }
+
+    #[tokio::test]
+    async fn forced_only() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // The default detectors don't take references for OCI sources
+        // unless the getter is forced.
+        assert_eq!(
+            detect("oci::ghcr.io/org/repo:v1", "/pwd", &getter::DETECTORS)
+                .await
+                .unwrap(),
+            "oci::oci://ghcr.io/org/repo:v1"
+        );
+        assert_eq!(
+            detect("registry:5000/repo:v1", "/pwd", &getter::DETECTORS)
+                .await
+                .unwrap(),
+            "registry:5000/repo:v1"
+        );
+    }
+}
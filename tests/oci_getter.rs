@@ -0,0 +1,651 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod oci_getter {
+    use super::common::serve;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use getter::getter::{Context, Getter, OCIGetter, GETTERS};
+    use getter::{Client, ClientMode, ErrorKind};
+    use hyper::header::{AUTHORIZATION, CONTENT_TYPE, HOST, LOCATION, WWW_AUTHENTICATE};
+    use hyper::{Body, Request, Response, StatusCode};
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use tempdir::TempDir;
+    use url::Url;
+
+    const MANIFEST_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+    // Auth is how the registry wants its clients to authenticate.
+    #[derive(Clone, Copy, PartialEq)]
+    enum Auth {
+        None,
+        Basic,
+        Bearer,
+    }
+
+    // Registry serves blobs and manifests the way an OCI registry does,
+    // asking for the credentials "user:pass" if it has to. It records the
+    // requests it receives.
+    #[derive(Clone)]
+    struct Registry {
+        blobs: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+        manifests: Arc<Mutex<BTreeMap<String, String>>>,
+        requests: Arc<Mutex<Vec<Recorded>>>,
+        auth: Auth,
+        // redirect is whether blobs are served from a separate location,
+        // like registries that keep them in object storage do.
+        redirect: bool,
+    }
+
+    // Recorded is the URI and authorization header of a request.
+    type Recorded = (String, Option<String>);
+
+    impl Registry {
+        fn new(auth: Auth) -> Registry {
+            Registry {
+                blobs: Arc::default(),
+                manifests: Arc::default(),
+                requests: Arc::default(),
+                auth,
+                redirect: false,
+            }
+        }
+
+        fn serve(&self) -> SocketAddr {
+            let registry = self.clone();
+            serve(move |req| registry.handle(req))
+        }
+
+        // push_blob stores data and returns a descriptor for it.
+        fn push_blob(&self, media_type: &str, data: &[u8], title: Option<&str>) -> Value {
+            let digest = format!("sha256:{}", hex::encode(Sha256::digest(data)));
+            self.blobs
+                .lock()
+                .unwrap()
+                .insert(digest.clone(), data.to_vec());
+
+            let mut descriptor = json!({
+                "mediaType": media_type,
+                "digest": digest,
+                "size": data.len(),
+            });
+            if let Some(title) = title {
+                descriptor["annotations"] = json!({ "org.opencontainers.image.title": title });
+            }
+            descriptor
+        }
+
+        // push_manifest stores a manifest with the given layers under tag,
+        // and returns its digest.
+        fn push_manifest(&self, tag: &str, layers: Vec<Value>) -> String {
+            let config = self.push_blob("application/vnd.oras.config.v1+json", b"{}", None);
+            let manifest = json!({
+                "schemaVersion": 2,
+                "mediaType": MANIFEST_TYPE,
+                "config": config,
+                "layers": layers,
+            })
+            .to_string();
+            let digest = format!("sha256:{}", hex::encode(Sha256::digest(&manifest)));
+
+            let mut manifests = self.manifests.lock().unwrap();
+            manifests.insert(tag.to_string(), manifest.clone());
+            manifests.insert(digest.clone(), manifest);
+            digest
+        }
+
+        fn handle(&self, req: Request<Body>) -> Response<Body> {
+            let path = req.uri().path().to_string();
+            let authorization = req
+                .headers()
+                .get(AUTHORIZATION)
+                .map(|v| v.to_str().unwrap().to_string());
+            self.requests
+                .lock()
+                .unwrap()
+                .push((req.uri().to_string(), authorization.clone()));
+
+            let basic = format!("Basic {}", base64::encode("user:pass"));
+
+            if path == "/token" {
+                if authorization.as_deref() != Some(basic.as_str()) {
+                    return status(StatusCode::UNAUTHORIZED);
+                }
+                return Response::new(Body::from(r#"{"token":"registry-token"}"#));
+            }
+
+            if let Some(digest) = path.strip_prefix("/storage/") {
+                assert_eq!(authorization, None);
+                return self.blob(digest);
+            }
+
+            let path = match path.strip_prefix("/v2/") {
+                Some(path) => path,
+                None => return status(StatusCode::NOT_FOUND),
+            };
+
+            let challenge = match self.auth {
+                Auth::None => None,
+                Auth::Basic if authorization.as_deref() != Some(basic.as_str()) => {
+                    Some(r#"Basic realm="registry""#.to_string())
+                }
+                Auth::Bearer if authorization.as_deref() != Some("Bearer registry-token") => {
+                    let host = req.headers()[HOST].to_str().unwrap();
+                    Some(format!(
+                        r#"Bearer realm="http://{}/token",service="registry",scope="repository:org/repo:pull""#,
+                        host
+                    ))
+                }
+                _ => None,
+            };
+            if let Some(challenge) = challenge {
+                return Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header(WWW_AUTHENTICATE, challenge)
+                    .body(Body::empty())
+                    .unwrap();
+            }
+
+            if let Some((_, reference)) = path.split_once("/manifests/") {
+                return match self.manifests.lock().unwrap().get(reference) {
+                    Some(manifest) => Response::builder()
+                        .header(CONTENT_TYPE, MANIFEST_TYPE)
+                        .body(Body::from(manifest.clone()))
+                        .unwrap(),
+                    None => registry_error("MANIFEST_UNKNOWN", "manifest unknown"),
+                };
+            }
+
+            if let Some((_, digest)) = path.split_once("/blobs/") {
+                if self.redirect {
+                    return Response::builder()
+                        .status(StatusCode::TEMPORARY_REDIRECT)
+                        .header(LOCATION, format!("/storage/{}", digest))
+                        .body(Body::empty())
+                        .unwrap();
+                }
+                return self.blob(digest);
+            }
+
+            status(StatusCode::NOT_FOUND)
+        }
+
+        fn blob(&self, digest: &str) -> Response<Body> {
+            match self.blobs.lock().unwrap().get(digest) {
+                Some(data) => Response::new(Body::from(data.clone())),
+                None => registry_error("BLOB_UNKNOWN", "blob unknown to registry"),
+            }
+        }
+
+        // authorizations returns the authorization headers that were sent
+        // to the given paths.
+        fn authorizations(&self, prefix: &str) -> Vec<Option<String>> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(uri, _)| uri.starts_with(prefix))
+                .map(|(_, authorization)| authorization.clone())
+                .collect()
+        }
+    }
+
+    fn status(status: StatusCode) -> Response<Body> {
+        Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn registry_error(code: &str, message: &str) -> Response<Body> {
+        let body = json!({ "errors": [{ "code": code, "message": message }] });
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    // tar_gz returns a gzipped tarball of the given files.
+    fn tar_gz(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    // getter returns a getter that reads credentials from a Docker config
+    // in dir with the given auths.
+    fn getter(dir: &TempDir, auths: Value) -> OCIGetter {
+        let path: PathBuf = dir.path().join("config.json");
+        fs::write(&path, json!({ "auths": auths }).to_string()).unwrap();
+
+        OCIGetter {
+            docker_config: Some(path),
+            ..OCIGetter::default()
+        }
+    }
+
+    fn url(addr: SocketAddr, reference: &str) -> Url {
+        Url::parse(&format!("oci://{}/{}?plain_http=true", addr, reference)).unwrap()
+    }
+
+    #[test]
+    fn registered() {
+        assert!(GETTERS.contains_key("oci"));
+    }
+
+    #[tokio::test]
+    async fn get_dir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        // ORAS pushes directories as gzipped tarballs with this annotation.
+        let mut dir = registry.push_blob(
+            "application/vnd.oci.image.layer.v1.tar",
+            &tar_gz(&[("dir/a.txt", "a"), ("dir/sub/b.txt", "b")]),
+            Some("dir"),
+        );
+        dir["annotations"]["io.deis.oras.content.unpack"] = json!("true");
+        registry.push_manifest(
+            "v1",
+            vec![
+                registry.push_blob("text/plain", b"Hello\n", Some("hello.txt")),
+                registry.push_blob("text/plain", b"World\n", Some("nested/world.txt")),
+                dir,
+                registry.push_blob(
+                    "application/vnd.oci.image.layer.v1.tar+gzip",
+                    &tar_gz(&[("layer.txt", "layer")]),
+                    None,
+                ),
+            ],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        getter(&tmp_dir, json!({}))
+            .get(&Context::default(), &dst, &url(addr, "org/repo:v1"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dst.join("hello.txt")).unwrap(),
+            "Hello\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dst.join("nested/world.txt")).unwrap(),
+            "World\n"
+        );
+        assert_eq!(fs::read_to_string(dst.join("dir/a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dst.join("dir/sub/b.txt")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dst.join("layer.txt")).unwrap(), "layer");
+        assert_eq!(fs::read_dir(&dst).unwrap().count(), 4);
+    }
+
+    #[tokio::test]
+    async fn get_dir_tar_media_type() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        // ORAS pushes files with the tar media type by default, which
+        // doesn't make them tarballs.
+        let registry = Registry::new(Auth::None);
+        let tarball = tar_gz(&[("a.txt", "a")]);
+        registry.push_manifest(
+            "v1",
+            vec![
+                registry.push_blob(
+                    "application/vnd.oci.image.layer.v1.tar",
+                    b"plugin",
+                    Some("plugin"),
+                ),
+                registry.push_blob(
+                    "application/vnd.oci.image.layer.v1.tar",
+                    &tarball,
+                    Some("archive.tar.gz"),
+                ),
+            ],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        getter(&tmp_dir, json!({}))
+            .get(&Context::default(), &dst, &url(addr, "org/repo:v1"))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("plugin")).unwrap(), "plugin");
+        assert_eq!(fs::read(dst.join("archive.tar.gz")).unwrap(), tarball);
+        assert_eq!(fs::read_dir(&dst).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("application/octet-stream", b"plugin", Some("plugin"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        getter(&tmp_dir, json!({}))
+            .get_file(&Context::default(), &dst, &url(addr, "org/repo:v1"))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "plugin");
+    }
+
+    #[tokio::test]
+    async fn get_file_layers() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        registry.push_manifest(
+            "v1",
+            vec![
+                registry.push_blob("text/plain", b"a", Some("a")),
+                registry.push_blob("text/plain", b"b", Some("b")),
+            ],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = getter(&tmp_dir, json!({}))
+            .get_file(
+                &Context::default(),
+                &tmp_dir.path().join("file"),
+                &url(addr, "org/repo:v1"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidSource);
+    }
+
+    #[tokio::test]
+    async fn get_digest() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        let digest = registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"v1", Some("version"))],
+        );
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"v2", Some("version"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        getter(&tmp_dir, json!({}))
+            .get_file(
+                &Context::default(),
+                &dst,
+                &url(addr, &format!("org/repo@{}", digest)),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "v1");
+    }
+
+    #[tokio::test]
+    async fn get_latest() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        registry.push_manifest(
+            "latest",
+            vec![registry.push_blob("text/plain", b"latest", Some("version"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        getter(&tmp_dir, json!({}))
+            .get_file(&Context::default(), &dst, &url(addr, "org/repo"))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "latest");
+    }
+
+    #[tokio::test]
+    async fn digest_mismatch() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        let layer = registry.push_blob("text/plain", b"good", Some("file"));
+        registry.blobs.lock().unwrap().insert(
+            layer["digest"].as_str().unwrap().to_string(),
+            b"evil".to_vec(),
+        );
+        registry.push_manifest("v1", vec![layer]);
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        let err = getter(&tmp_dir, json!({}))
+            .get(&Context::default(), &dst, &url(addr, "org/repo:v1"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::ChecksumMismatch);
+        assert_eq!(fs::read_dir(&dst).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn bearer_auth() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::Bearer);
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"private", Some("file"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        let auths = json!({ addr.to_string(): { "auth": base64::encode("user:pass") } });
+        getter(&tmp_dir, auths)
+            .get_file(&Context::default(), &dst, &url(addr, "org/repo:v1"))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "private");
+
+        // The token is only asked for once, and then used for the blob too.
+        let token = registry.authorizations("/token?");
+        assert_eq!(token.len(), 1);
+        assert_eq!(
+            registry.authorizations("/v2/"),
+            [
+                None,
+                Some("Bearer registry-token".to_string()),
+                Some("Bearer registry-token".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn basic_auth() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::Basic);
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"private", Some("file"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        let auths = json!({
+            format!("http://{}/v1/", addr): { "username": "user", "password": "pass" },
+        });
+        getter(&tmp_dir, auths)
+            .get_file(&Context::default(), &dst, &url(addr, "org/repo:v1"))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "private");
+    }
+
+    #[tokio::test]
+    async fn unauthorized() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::Bearer);
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"private", Some("file"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = getter(&tmp_dir, json!({ "other.example.com": { "auth": "eDp5" } }))
+            .get_file(
+                &Context::default(),
+                &tmp_dir.path().join("file"),
+                &url(addr, "org/repo:v1"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Authentication);
+    }
+
+    #[tokio::test]
+    async fn redirect() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let mut registry = Registry::new(Auth::Bearer);
+        registry.redirect = true;
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"stored", Some("file"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        let auths = json!({ addr.to_string(): { "auth": base64::encode("user:pass") } });
+        getter(&tmp_dir, auths)
+            .get_file(&Context::default(), &dst, &url(addr, "org/repo:v1"))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "stored");
+        assert_eq!(registry.authorizations("/storage/"), [None]);
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = getter(&tmp_dir, json!({}))
+            .get(
+                &Context::default(),
+                &tmp_dir.path().join("dst"),
+                &url(addr, "org/repo:missing"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("MANIFEST_UNKNOWN"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn title_outside() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"evil", Some("../evil"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = getter(&tmp_dir, json!({}))
+            .get(
+                &Context::default(),
+                &tmp_dir.path().join("dst"),
+                &url(addr, "org/repo:v1"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidSource);
+        assert!(!tmp_dir.path().join("evil").exists());
+    }
+
+    #[tokio::test]
+    async fn client() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let registry = Registry::new(Auth::None);
+        registry.push_manifest(
+            "v1",
+            vec![registry.push_blob("text/plain", b"Hello\n", Some("hello.txt"))],
+        );
+        let addr = registry.serve();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        Client::new(
+            format!("oci::{}/org/repo:v1?plain_http=true", addr),
+            &dst,
+            ClientMode::Any,
+        )
+        .get()
+        .await
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dst.join("hello.txt")).unwrap(),
+            "Hello\n"
+        );
+    }
+}
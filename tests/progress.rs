@@ -0,0 +1,155 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod progress {
+    use super::common::serve;
+    use getter::getter::{Context, FileGetter, Getter, HttpGetter};
+    use getter::{Client, ClientMode, ProgressListener};
+    use hyper::{Body, Response};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use tempdir::TempDir;
+    use url::Url;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Event {
+        Start(String, Option<u64>),
+        Progress(String, u64, Option<u64>),
+        Finish(String),
+    }
+
+    // Recorder records every event it is notified about.
+    #[derive(Debug, Default)]
+    struct Recorder(Mutex<Vec<Event>>);
+
+    impl ProgressListener for Recorder {
+        fn start(&self, name: &str, total: Option<u64>) {
+            let event = Event::Start(name.to_string(), total);
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn progress(&self, name: &str, received: u64, total: Option<u64>) {
+            let event = Event::Progress(name.to_string(), received, total);
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn finish(&self, name: &str) {
+            let event = Event::Finish(name.to_string());
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    impl Recorder {
+        // summary collapses the progress events of each file into the last
+        // one, so that tests don't depend on how the data was chunked.
+        fn summary(&self) -> Vec<Event> {
+            let mut events: Vec<Event> = Vec::new();
+            for event in self.0.lock().unwrap().drain(..) {
+                if let (Some(Event::Progress(last, ..)), Event::Progress(name, ..)) =
+                    (events.last(), &event)
+                {
+                    if last == name {
+                        events.pop();
+                    }
+                }
+                events.push(event);
+            }
+            events
+        }
+    }
+
+    fn context(recorder: &Arc<Recorder>) -> Context {
+        let mut ctx = Context::default();
+        ctx.progress = Some(recorder.clone());
+        ctx
+    }
+
+    #[tokio::test]
+    async fn http_get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let addr = serve(|_| Response::new(Body::from("Hello\n")));
+        let url = Url::parse(&format!("http://{}/file", addr)).unwrap();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let recorder = Arc::new(Recorder::default());
+
+        HttpGetter::default()
+            .get_file(&context(&recorder), &tmp_dir.path().join("file"), &url)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            recorder.summary(),
+            [
+                Event::Start(url.to_string(), Some(6)),
+                Event::Progress(url.to_string(), 6, Some(6)),
+                Event::Finish(url.to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn file_get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let src = tmp_dir.path().join("src");
+        fs::write(&src, "Hello\n").unwrap();
+        let url = Url::from_file_path(&src).unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+
+        FileGetter::default()
+            .get_file(&context(&recorder), &tmp_dir.path().join("dst"), &url)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            recorder.summary(),
+            [
+                Event::Start(url.to_string(), Some(6)),
+                Event::Progress(url.to_string(), 6, Some(6)),
+                Event::Finish(url.to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn client_get_archive() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let archive = fs::read(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/decompress/multiple.tar.gz"),
+        )
+        .unwrap();
+        let len = archive.len() as u64;
+        let addr = serve(move |_| Response::new(Body::from(archive.clone())));
+        let url = format!("http://{}/multiple.tar.gz", addr);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let recorder = Arc::new(Recorder::default());
+
+        let mut client = Client::new(&url, tmp_dir.path().join("dst"), ClientMode::Dir);
+        client.progress = Some(recorder.clone());
+        client.get().await.unwrap();
+
+        // The download is reported first, followed by every extracted file.
+        assert_eq!(
+            recorder.summary(),
+            [
+                Event::Start(url.clone(), Some(len)),
+                Event::Progress(url.clone(), len, Some(len)),
+                Event::Finish(url),
+                Event::Start("a/file1".to_string(), Some(4)),
+                Event::Progress("a/file1".to_string(), 4, Some(4)),
+                Event::Finish("a/file1".to_string()),
+                Event::Start("b/c/file2".to_string(), Some(4)),
+                Event::Progress("b/c/file2".to_string(), 4, Some(4)),
+                Event::Finish("b/c/file2".to_string()),
+            ]
+        );
+    }
+}
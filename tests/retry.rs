@@ -0,0 +1,148 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod retry {
+    use super::common::serve;
+    use getter::getter::{Context, Getter, HttpGetter};
+    use getter::{Client, ClientMode, ErrorKind, RetryPolicy};
+    use hyper::{Body, Response, StatusCode};
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tempdir::TempDir;
+    use url::Url;
+
+    // flaky serves "Hello\n" once it has answered the given number of
+    // requests with the failing status. It returns the address and the
+    // request counter.
+    fn flaky(
+        failures: usize,
+        status: StatusCode,
+        retry_after: Option<&'static str>,
+    ) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        let addr = serve(move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                let mut resp = Response::builder().status(status);
+                if let Some(retry_after) = retry_after {
+                    resp = resp.header("Retry-After", retry_after);
+                }
+                return resp.body(Body::empty()).unwrap();
+            }
+            Response::new(Body::from("Hello\n"))
+        });
+
+        (format!("http://{}/file", addr), requests)
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(100),
+        }
+    }
+
+    async fn get(src: &str, retry: RetryPolicy) -> (getter::Result<()>, TempDir) {
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let mut client = Client::new(src, tmp_dir.path().join("file"), ClientMode::File);
+        client.retry = retry;
+        let result = client.get().await;
+
+        (result, tmp_dir)
+    }
+
+    #[tokio::test]
+    async fn server_error() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (src, requests) = flaky(2, StatusCode::SERVICE_UNAVAILABLE, None);
+
+        let (result, tmp_dir) = get(&src, policy(3)).await;
+
+        result.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("file")).unwrap(),
+            "Hello\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn attempts_exhausted() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (src, requests) = flaky(5, StatusCode::BAD_GATEWAY, None);
+
+        let (result, _tmp_dir) = get(&src, policy(3)).await;
+
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::HttpStatus(502));
+        assert!(err.is_retryable());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn not_retryable() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (src, requests) = flaky(5, StatusCode::NOT_FOUND, None);
+
+        let (result, _tmp_dir) = get(&src, policy(3)).await;
+
+        assert!(!result.unwrap_err().is_retryable());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn disabled() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (src, requests) = flaky(1, StatusCode::INTERNAL_SERVER_ERROR, None);
+
+        let (result, _tmp_dir) = get(&src, RetryPolicy::none()).await;
+
+        assert!(result.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (src, requests) = flaky(1, StatusCode::TOO_MANY_REQUESTS, Some("1"));
+
+        let retry = RetryPolicy {
+            max_backoff: Duration::from_secs(5),
+            ..policy(2)
+        };
+        let start = Instant::now();
+        let (result, _tmp_dir) = get(&src, retry).await;
+
+        result.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn retry_after_error() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let (src, _) = flaky(1, StatusCode::TOO_MANY_REQUESTS, Some("120"));
+        let url = Url::parse(&src).unwrap();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let err = HttpGetter::default()
+            .get_file(&Context::default(), &tmp_dir.path().join("file"), &url)
+            .await
+            .unwrap_err();
+
+        assert!(err.is_retryable());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(120)));
+    }
+}
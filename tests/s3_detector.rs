@@ -0,0 +1,68 @@
+mod s3_detector {
+    use getter::{
+        detect,
+        detector::{Detector, S3Detector},
+        ErrorKind,
+    };
+
+    macro_rules! detect_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            # [tokio::test]
+            async fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let (input, expected) = $value;
+
+                let pwd = "/pwd";
+                let detectors: Vec<Box<dyn Detector>> = vec![Box::new(S3Detector)];
+                assert_eq !(expected, detect(input, pwd, &detectors).await.unwrap())
+            }
+        )*
+        }
+    }
+
+    detect_tests! {
+        // Virtual hosted style
+        test_detect_1: ("bucket.s3.amazonaws.com/foo",
+        "s3::https://s3.amazonaws.com/bucket/foo"),
+        test_detect_2: ("bucket.s3.amazonaws.com/foo/bar",
+        "s3::https://s3.amazonaws.com/bucket/foo/bar"),
+        test_detect_3: ("bucket.s3.amazonaws.com/foo/bar.baz",
+        "s3::https://s3.amazonaws.com/bucket/foo/bar.baz"),
+        test_detect_4: ("bucket.s3-eu-west-1.amazonaws.com/foo",
+        "s3::https://s3-eu-west-1.amazonaws.com/bucket/foo"),
+        test_detect_5: ("bucket.s3.eu-west-1.amazonaws.com/foo",
+        "s3::https://s3.eu-west-1.amazonaws.com/bucket/foo"),
+        // Path style
+        test_detect_6: ("s3.amazonaws.com/bucket/foo",
+        "s3::https://s3.amazonaws.com/bucket/foo"),
+        test_detect_7: ("s3-eu-west-1.amazonaws.com/bucket/foo",
+        "s3::https://s3-eu-west-1.amazonaws.com/bucket/foo"),
+        // Misc
+        test_detect_8: ("s3-eu-west-1.amazonaws.com/bucket/foo?version=1234",
+        "s3::https://s3-eu-west-1.amazonaws.com/bucket/foo?version=1234"),
+        test_detect_9: ("s3.amazonaws.com/bucket/foo//bar",
+        "s3::https://s3.amazonaws.com/bucket/foo//bar"),
+        test_detect_10: ("s3::bucket.s3.amazonaws.com/foo",
+        "s3::https://s3.amazonaws.com/bucket/foo"),
+    }
+
+    #[tokio::test]
+    async fn test_detect_other() {
+        let (_, ok) = S3Detector
+            .detect("github.com/foo/bar", "/pwd")
+            .await
+            .unwrap();
+        assert!(!ok);
+    }
+
+    #[tokio::test]
+    async fn test_detect_invalid() {
+        let err = S3Detector
+            .detect("a.b.c.d.e.f.amazonaws.com/foo", "/pwd")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidSource);
+    }
+}
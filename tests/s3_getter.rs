@@ -0,0 +1,424 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod s3_getter {
+    use super::common::serve;
+    use getter::getter::{Context, Getter, S3Getter, GETTERS};
+    use getter::{Client, ClientMode, ErrorKind};
+    use hyper::header::AUTHORIZATION;
+    use hyper::{Body, Request, Response, StatusCode};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use tempdir::TempDir;
+    use url::Url;
+
+    const CREDENTIALS: &str = "aws_access_key_id=AKID&aws_access_key_secret=SECRET";
+
+    // Bucket serves the objects of a single bucket called "bucket" the way
+    // S3 does, listing one object per page. It records the requests it
+    // receives.
+    #[derive(Clone, Default)]
+    struct Bucket {
+        objects: Arc<BTreeMap<String, String>>,
+        requests: Arc<Mutex<Vec<Request<Body>>>>,
+    }
+
+    impl Bucket {
+        fn new(objects: &[(&str, &str)]) -> Bucket {
+            Bucket {
+                objects: Arc::new(
+                    objects
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                ),
+                ..Bucket::default()
+            }
+        }
+
+        // serve starts the server and returns its URL for the given path
+        // and query.
+        fn serve(&self, path: &str, query: &str) -> Url {
+            let bucket = self.clone();
+            let addr = serve(move |req| bucket.handle(req));
+
+            Url::parse(&format!("http://{}{}?{}", addr, path, query)).unwrap()
+        }
+
+        fn handle(&self, req: Request<Body>) -> Response<Body> {
+            let path = req.uri().path().to_string();
+            let query: BTreeMap<String, String> =
+                url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+                    .into_owned()
+                    .collect();
+            self.requests.lock().unwrap().push(req);
+
+            if path == "/bucket" {
+                return self.list(&query);
+            }
+
+            let key = path.strip_prefix("/bucket/").unwrap_or_default();
+            let key = percent_decode(key);
+            if key.starts_with("denied") {
+                return error(StatusCode::FORBIDDEN, "AccessDenied", "Access Denied");
+            }
+            match self.objects.get(&key) {
+                Some(data) => Response::new(Body::from(data.clone())),
+                None => error(
+                    StatusCode::NOT_FOUND,
+                    "NoSuchKey",
+                    "The specified key does not exist.",
+                ),
+            }
+        }
+
+        fn list(&self, query: &BTreeMap<String, String>) -> Response<Body> {
+            assert_eq!(query.get("list-type").map(String::as_str), Some("2"));
+
+            let prefix = query.get("prefix").cloned().unwrap_or_default();
+            let after = query.get("continuation-token").cloned().unwrap_or_default();
+            let mut keys = self
+                .objects
+                .keys()
+                .filter(|k| k.starts_with(&prefix) && **k > after);
+
+            let mut xml = String::from(
+                r#"<?xml version="1.0" encoding="UTF-8"?><ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+            );
+            if let Some(key) = keys.next() {
+                xml.push_str(&format!("<Contents><Key>{}</Key></Contents>", key));
+                if keys.next().is_some() {
+                    xml.push_str(&format!(
+                        "<IsTruncated>true</IsTruncated><NextContinuationToken>{}</NextContinuationToken>",
+                        key
+                    ));
+                }
+            }
+            xml.push_str("</ListBucketResult>");
+
+            Response::new(Body::from(xml))
+        }
+
+        // authorization returns the authorization headers of the requests
+        // received so far.
+        fn authorization(&self) -> Vec<Option<String>> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|req| {
+                    req.headers()
+                        .get(AUTHORIZATION)
+                        .map(|v| v.to_str().unwrap().to_string())
+                })
+                .collect()
+        }
+    }
+
+    fn error(status: StatusCode, code: &str, message: &str) -> Response<Body> {
+        Response::builder()
+            .status(status)
+            .body(Body::from(format!(
+                "<Error><Code>{}</Code><Message>{}</Message></Error>",
+                code, message
+            )))
+            .unwrap()
+    }
+
+    fn percent_decode(s: &str) -> String {
+        url::form_urlencoded::parse(format!("k={}", s.replace('+', "%2B")).as_bytes())
+            .next()
+            .unwrap()
+            .1
+            .into_owned()
+    }
+
+    #[test]
+    fn registered() {
+        assert!(GETTERS.contains_key("s3"));
+    }
+
+    #[tokio::test]
+    async fn get_file() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bucket = Bucket::new(&[("dir/hello world.txt", "Hello\n")]);
+        let url = bucket.serve("/bucket/dir/hello%20world.txt", CREDENTIALS);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("file");
+
+        S3Getter::default()
+            .get_file(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst).unwrap(), "Hello\n");
+
+        let authorization = bucket.authorization().remove(0).unwrap();
+        assert!(
+            authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"),
+            "{}",
+            authorization
+        );
+        assert!(authorization.contains("/us-east-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="));
+    }
+
+    #[tokio::test]
+    async fn get_file_version_region_token() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bucket = Bucket::new(&[("file", "Hello\n")]);
+        let url = bucket.serve(
+            "/bucket/file",
+            &format!(
+                "{}&aws_access_key_token=TOKEN&version=v1&region=eu-central-1",
+                CREDENTIALS
+            ),
+        );
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        S3Getter::default()
+            .get_file(&Context::default(), &tmp_dir.path().join("file"), &url)
+            .await
+            .unwrap();
+
+        let requests = bucket.requests.lock().unwrap();
+        assert_eq!(requests[0].uri().query(), Some("versionId=v1"));
+        assert_eq!(requests[0].headers()["x-amz-security-token"], "TOKEN");
+
+        let authorization = requests[0].headers()[AUTHORIZATION].to_str().unwrap();
+        assert!(authorization.contains("/eu-central-1/s3/aws4_request"));
+        assert!(authorization.contains("x-amz-date;x-amz-security-token"));
+    }
+
+    #[tokio::test]
+    async fn get_dir() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bucket = Bucket::new(&[
+            ("dir/a.txt", "a"),
+            ("dir/empty/", ""),
+            ("dir/sub/b.txt", "b"),
+            ("dirx/c.txt", "c"),
+        ]);
+        let url = bucket.serve("/bucket/dir", CREDENTIALS);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        S3Getter::default()
+            .get(&Context::default(), &dst, &url)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dst.join("sub/b.txt")).unwrap(), "b");
+        assert!(!dst.join("empty").exists());
+        assert!(!dst.join("c.txt").exists());
+        assert!(!tmp_dir.path().join("dirx").exists());
+    }
+
+    #[tokio::test]
+    async fn get_dir_outside() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bucket = Bucket::new(&[("dir/../evil", "evil")]);
+        let url = bucket.serve("/bucket/dir/", CREDENTIALS);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = S3Getter::default()
+            .get(&Context::default(), &tmp_dir.path().join("dst"), &url)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidSource);
+        assert!(!tmp_dir.path().join("evil").exists());
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bucket = Bucket::new(&[]);
+        let url = bucket.serve(
+            "/bucket/missing",
+            &format!("{}&aws_access_key_token=TOKEN", CREDENTIALS),
+        );
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = S3Getter::default()
+            .get_file(&Context::default(), &tmp_dir.path().join("file"), &url)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("NoSuchKey"), "{}", err);
+
+        // The secrets in the URL are redacted.
+        let query = err.url().unwrap().query().unwrap();
+        assert!(
+            query.contains("aws_access_key_secret=redacted"),
+            "{}",
+            query
+        );
+        assert!(query.contains("aws_access_key_token=redacted"), "{}", query);
+    }
+
+    #[tokio::test]
+    async fn access_denied() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bucket = Bucket::new(&[]);
+        let url = bucket.serve("/bucket/denied", CREDENTIALS);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let err = S3Getter::default()
+            .get_file(&Context::default(), &tmp_dir.path().join("file"), &url)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Authentication);
+    }
+
+    #[tokio::test]
+    async fn client() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let bucket = Bucket::new(&[("dir/a.txt", "a"), ("dir/b.txt", "b")]);
+        let url = bucket.serve("/bucket/dir/", CREDENTIALS);
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+
+        Client::new(format!("s3::{}", url), &dst, ClientMode::Any)
+            .get()
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dst.join("b.txt")).unwrap(), "b");
+    }
+
+    #[tokio::test]
+    async fn credentials() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let shared = tmp_dir.path().join("credentials");
+        fs::write(
+            &shared,
+            "[default]\naws_access_key_id = SHARED\naws_secret_access_key = SECRET\n\n\
+             [other]\naws_access_key_id = OTHER\naws_secret_access_key = SECRET\n",
+        )
+        .unwrap();
+        let home = tmp_dir.path().join("home");
+        fs::create_dir_all(home.join(".aws")).unwrap();
+        fs::write(
+            home.join(".aws").join("credentials"),
+            "[default]\naws_access_key_id = HOME\naws_secret_access_key = SECRET\n",
+        )
+        .unwrap();
+
+        let bucket = Bucket::new(&[("file", "Hello\n")]);
+        let url = bucket.serve("/bucket/file", "");
+        let get = |vars: &[(&str, &str)]| {
+            let vars: BTreeMap<String, String> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let getter = S3Getter {
+                vars: Some(Arc::new(move |name| vars.get(name).cloned())),
+                ..S3Getter::default()
+            };
+            let dst = tmp_dir.path().join("file");
+            let url = url.clone();
+            async move {
+                getter
+                    .get_file(&Context::default(), &dst, &url)
+                    .await
+                    .unwrap();
+            }
+        };
+
+        // Without any credentials the request isn't signed.
+        get(&[]).await;
+
+        get(&[("HOME", home.to_str().unwrap())]).await;
+
+        let shared = shared.to_str().unwrap();
+        get(&[("AWS_SHARED_CREDENTIALS_FILE", shared)]).await;
+
+        get(&[
+            ("AWS_SHARED_CREDENTIALS_FILE", shared),
+            ("AWS_PROFILE", "other"),
+        ])
+        .await;
+
+        get(&[
+            ("AWS_SHARED_CREDENTIALS_FILE", shared),
+            ("AWS_ACCESS_KEY_ID", "ENV"),
+            ("AWS_SECRET_ACCESS_KEY", "SECRET"),
+        ])
+        .await;
+
+        // Empty variables count as unset.
+        get(&[
+            ("AWS_SHARED_CREDENTIALS_FILE", shared),
+            ("AWS_ACCESS_KEY_ID", ""),
+            ("AWS_SECRET_ACCESS_KEY", "SECRET"),
+        ])
+        .await;
+
+        let credentials: Vec<_> = bucket
+            .authorization()
+            .into_iter()
+            .map(|authorization| authorization.map(|a| a.split('/').next().unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            credentials,
+            [
+                None,
+                Some("AWS4-HMAC-SHA256 Credential=HOME".to_string()),
+                Some("AWS4-HMAC-SHA256 Credential=SHARED".to_string()),
+                Some("AWS4-HMAC-SHA256 Credential=OTHER".to_string()),
+                Some("AWS4-HMAC-SHA256 Credential=ENV".to_string()),
+                Some("AWS4-HMAC-SHA256 Credential=SHARED".to_string()),
+            ]
+        );
+    }
+
+    macro_rules! invalid_url_tests {
+        ($($name:ident: $url:expr,)*) => {
+        $(
+            #[tokio::test]
+            async fn $name() {
+                let _ = env_logger::builder().is_test(true).try_init();
+
+                let tmp_dir = TempDir::new("rs-getter").unwrap();
+                let url = Url::parse($url).unwrap();
+
+                let err = S3Getter::default()
+                    .get_file(&Context::default(), &tmp_dir.path().join("file"), &url)
+                    .await
+                    .unwrap_err();
+
+                assert_eq!(err.kind(), ErrorKind::InvalidSource);
+            }
+        )*
+        }
+    }
+
+    invalid_url_tests! {
+        invalid_no_bucket: "http://127.0.0.1:1/",
+        invalid_no_key: "http://127.0.0.1:1/bucket",
+        invalid_aws_host: "https://a.b.c.s3.eu-west-1.amazonaws.com/key",
+        invalid_secret_missing: "http://127.0.0.1:1/bucket/key?aws_access_key_id=AKID",
+    }
+}
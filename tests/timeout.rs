@@ -0,0 +1,178 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod common;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod timeout {
+    use super::common::serve;
+    use bytes::Bytes;
+    use futures_util::{stream, StreamExt};
+    use getter::getter::{Context, Getter, HttpGetter};
+    use getter::{CancellationToken, Client, ClientMode, ErrorKind, RetryPolicy, Timeouts};
+    use hyper::{Body, Response};
+    use std::io;
+    use std::time::Duration;
+    use tempdir::TempDir;
+    use tokio::net::TcpListener;
+    use url::Url;
+
+    // stalled serves the first chunk of a file and then stops sending.
+    fn stalled() -> String {
+        let addr = serve(|_| {
+            let chunks =
+                stream::iter([Ok::<_, io::Error>(Bytes::from("Hello\n"))]).chain(stream::pending());
+            Response::new(Body::wrap_stream(chunks))
+        });
+
+        format!("http://{}/file", addr)
+    }
+
+    // silent accepts connections but never answers them.
+    async fn silent() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut conns = Vec::new();
+            while let Ok((conn, _)) = listener.accept().await {
+                conns.push(conn);
+            }
+        });
+
+        format!("http://{}/file", addr)
+    }
+
+    fn client(src: &str, tmp_dir: &TempDir, timeouts: Timeouts) -> Client {
+        let mut client = Client::new(src, tmp_dir.path().join("file"), ClientMode::File);
+        client.retry = RetryPolicy::none();
+        client.timeouts = timeouts;
+        client
+    }
+
+    #[tokio::test]
+    async fn read_body() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let timeouts = Timeouts {
+            read: Some(Duration::from_millis(200)),
+            ..Timeouts::none()
+        };
+
+        let err = client(&stalled(), &tmp_dir, timeouts)
+            .get()
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert!(err.is_retryable());
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn read_headers() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let timeouts = Timeouts {
+            read: Some(Duration::from_millis(200)),
+            ..Timeouts::none()
+        };
+
+        let err = client(&silent().await, &tmp_dir, timeouts)
+            .get()
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+    }
+
+    #[tokio::test]
+    async fn total() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let timeouts = Timeouts {
+            total: Some(Duration::from_millis(300)),
+            ..Timeouts::none()
+        };
+
+        let mut client = client(&stalled(), &tmp_dir, timeouts);
+        // Retries don't extend the deadline.
+        client.retry = RetryPolicy::default();
+        let err = client.get().await.unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert!(!tmp_dir.path().join("file").exists());
+    }
+
+    #[tokio::test]
+    async fn cancel() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let client = client(&stalled(), &tmp_dir, Timeouts::none());
+
+        let cancel = client.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            cancel.cancel();
+        });
+
+        let err = client.get().await.unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        assert!(!err.is_retryable());
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancel_before() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let addr = serve(|_| Response::new(Body::from("Hello\n")));
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+
+        let client = client(&format!("http://{}/file", addr), &tmp_dir, Timeouts::none());
+        client.cancel.cancel();
+
+        let err = client.get().await.unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        assert!(!tmp_dir.path().join("file").exists());
+    }
+
+    #[tokio::test]
+    async fn cancel_keeps_existing() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let tmp_dir = TempDir::new("rs-getter").unwrap();
+        let dst = tmp_dir.path().join("dst");
+        std::fs::create_dir(&dst).unwrap();
+        std::fs::write(dst.join("keep"), "keep").unwrap();
+
+        let mut client = Client::new(stalled(), &dst, ClientMode::Any);
+        client.cancel = CancellationToken::new();
+        client.cancel.cancel();
+
+        let err = client.get().await.unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        assert!(dst.join("keep").exists());
+    }
+
+    #[tokio::test]
+    async fn stream() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let url = Url::parse(&stalled()).unwrap();
+
+        let mut ctx = Context::default();
+        ctx.timeouts.read = Some(Duration::from_millis(200));
+
+        let mut stream = HttpGetter::default().stream(&ctx, &url).await.unwrap();
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "Hello\n");
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}